@@ -0,0 +1,299 @@
+use crate::plan::Job;
+use crate::template::Template;
+use clap::ValueEnum;
+use std::collections::{HashMap, HashSet};
+use std::ffi::OsString;
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+/// what to do if outputs collide, see [`find`]
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OnCollision {
+    /// don't process anything and list the collisions
+    Abort,
+    /// add --collision-suffix to the outputs that collide
+    Suffix,
+}
+
+/// outputs that would destroy each other or an input
+pub enum Collision {
+    /// several inputs would be written to the same output
+    Shared {
+        output: PathBuf,
+        inputs: Vec<PathBuf>,
+    },
+    /// the output is an input itself, which ffmpeg would replace while still reading it
+    OverwritesInput { output: PathBuf, input: PathBuf },
+}
+
+/// every output that is shared by several inputs or is an input itself, in the order of the inputs
+pub fn find(jobs: &[Job]) -> Vec<Collision> {
+    let input_identities = jobs
+        .iter()
+        .map(|job| (identity(&job.input.path), &job.input.path))
+        .collect::<HashMap<_, _>>();
+
+    let mut by_output: Vec<(PathBuf, PathBuf, Vec<PathBuf>)> = vec![];
+    let mut positions = HashMap::new();
+
+    for job in jobs {
+        let Ok(plan) = &job.plan else { continue };
+        let identity = identity(&plan.output);
+
+        let position = *positions.entry(identity.clone()).or_insert_with(|| {
+            by_output.push((identity, plan.output.clone(), vec![]));
+            by_output.len() - 1
+        });
+        by_output[position].2.push(job.input.path.clone());
+    }
+
+    let mut collisions = vec![];
+
+    for (identity, output, inputs) in by_output {
+        if let Some(input) = input_identities.get(&identity) {
+            collisions.push(Collision::OverwritesInput {
+                output: output.clone(),
+                input: input.to_path_buf(),
+            });
+        }
+
+        if inputs.len() > 1 {
+            collisions.push(Collision::Shared { output, inputs });
+        }
+    }
+
+    collisions
+}
+
+/// gives every colliding output a name of its own by adding `suffix` before the extension. `suffix` is rendered with
+/// `{{n}}` counting up until the name is free. the first input of a shared output keeps the name, unless it is an
+/// input itself.
+pub fn disambiguate(jobs: &mut [Job], suffix: &Template) {
+    let inputs = jobs
+        .iter()
+        .map(|job| identity(&job.input.path))
+        .collect::<HashSet<PathBuf>>();
+    let mut taken = jobs
+        .iter()
+        .filter_map(|job| job.plan.as_ref().ok())
+        .map(|plan| identity(&plan.output))
+        .collect::<HashSet<PathBuf>>();
+    let mut claimed = HashSet::new();
+
+    for job in jobs.iter_mut() {
+        let Ok(plan) = &mut job.plan else { continue };
+        let identity = identity(&plan.output);
+
+        if !inputs.contains(&identity) && claimed.insert(identity) {
+            continue;
+        }
+
+        for n in 1.. {
+            let candidate =
+                with_suffix(&plan.output, &suffix.render(|_| Some(n.to_string().into())));
+            let candidate_identity = self::identity(&candidate);
+
+            if !inputs.contains(&candidate_identity) && taken.insert(candidate_identity.clone()) {
+                claimed.insert(candidate_identity);
+                plan.output = candidate;
+                break;
+            }
+        }
+    }
+}
+
+/// `out/clip.mkv` with `_1` becomes `out/clip_1.mkv`
+fn with_suffix(output: &Path, suffix: &OsString) -> PathBuf {
+    let mut file_name = output.file_stem().unwrap_or_default().to_os_string();
+    file_name.push(suffix);
+
+    if let Some(extension) = output.extension() {
+        file_name.push(".");
+        file_name.push(extension);
+    }
+
+    output.with_file_name(file_name)
+}
+
+/// the same path for every way of referring to the same file, as far as that can be told. outputs usually don't exist
+/// yet, so only their directory is resolved on disk if possible.
+pub fn identity(path: &Path) -> PathBuf {
+    if let Ok(canonical) = fs::canonicalize(path) {
+        return canonical;
+    }
+
+    let absolute = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    let mut normalized = PathBuf::new();
+
+    for component in absolute.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+
+    match (
+        normalized
+            .parent()
+            .and_then(|parent| fs::canonicalize(parent).ok()),
+        normalized.file_name(),
+    ) {
+        (Some(parent), Some(file_name)) => parent.join(file_name),
+        _ => normalized,
+    }
+}
+
+impl Display for Collision {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Collision::Shared { output, inputs } => {
+                write!(
+                    f,
+                    "{} would be written by {} files:",
+                    output.display(),
+                    inputs.len()
+                )?;
+
+                for input in inputs {
+                    write!(f, "\n  {}", input.display())?;
+                }
+
+                Ok(())
+            }
+            Collision::OverwritesInput { output, input } => {
+                write!(
+                    f,
+                    "{} would overwrite the input {}",
+                    output.display(),
+                    input.display()
+                )
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::Input;
+    use crate::plan::Plan;
+
+    /// below a directory that doesn't exist, so nothing on disk gets in the way
+    const DIR: &str = "/nonexistent-ffzap-test";
+
+    fn job(input: &str, output: Option<&str>) -> Job {
+        Job {
+            input: Input {
+                path: Path::new(DIR).join(input),
+                index: 1,
+                base: None,
+            },
+            plan: match output {
+                Some(output) => Ok(Plan {
+                    output: Path::new(DIR).join(output),
+                    input_options: vec![],
+                }),
+                None => Err("ffprobe failed".to_string()),
+            },
+        }
+    }
+
+    fn disambiguated(mut jobs: Vec<Job>) -> Vec<Option<PathBuf>> {
+        let suffix = Template::parse("_{{n}}", |name| name == "n").unwrap();
+        disambiguate(&mut jobs, &suffix);
+
+        jobs.into_iter()
+            .map(|job| {
+                job.plan
+                    .ok()
+                    .map(|plan| plan.output.strip_prefix(DIR).unwrap().to_path_buf())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn shared_outputs_are_numbered() {
+        let outputs = disambiguated(vec![
+            job("a/clip.mp4", Some("out/clip.mkv")),
+            job("b/clip.mp4", Some("out/clip.mkv")),
+            job("c/clip.mp4", None),
+            job("d/clip.mp4", Some("out/clip.mkv")),
+        ]);
+
+        assert_eq!(
+            outputs,
+            [
+                Some("out/clip.mkv".into()),
+                Some("out/clip_1.mkv".into()),
+                None,
+                Some("out/clip_2.mkv".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn planned_names_are_skipped() {
+        let outputs = disambiguated(vec![
+            job("a/clip.mp4", Some("out/clip.mkv")),
+            job("b/clip.mp4", Some("out/clip.mkv")),
+            job("clip_1.mp4", Some("out/clip_1.mkv")),
+        ]);
+
+        assert_eq!(
+            outputs,
+            [
+                Some("out/clip.mkv".into()),
+                Some("out/clip_2.mkv".into()),
+                Some("out/clip_1.mkv".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn inputs_are_never_overwritten() {
+        let outputs = disambiguated(vec![
+            job("clip.mkv", Some("clip.mkv")),
+            job("clip_1.mkv", Some("other.mkv")),
+        ]);
+
+        assert_eq!(
+            outputs,
+            [Some("clip_2.mkv".into()), Some("other.mkv".into())]
+        );
+    }
+
+    #[test]
+    fn suffix_goes_before_the_extension() {
+        let suffix = OsString::from("_1");
+
+        assert_eq!(
+            with_suffix(Path::new("out/clip.mkv"), &suffix),
+            Path::new("out/clip_1.mkv")
+        );
+        assert_eq!(
+            with_suffix(Path::new("out/clip"), &suffix),
+            Path::new("out/clip_1")
+        );
+    }
+
+    #[test]
+    fn finds_shared_outputs_and_overwritten_inputs() {
+        let collisions = find(&[
+            job("a.mp4", Some("out.mkv")),
+            job("b.mp4", Some("./out.mkv")),
+            job("out.mkv", Some("c.mkv")),
+            job("d.mp4", Some("d.mp4")),
+        ]);
+
+        assert_eq!(collisions.len(), 3);
+        assert!(matches!(&collisions[0], Collision::OverwritesInput { .. }));
+        assert!(matches!(&collisions[1], Collision::Shared { inputs, .. } if inputs.len() == 2));
+        assert!(
+            matches!(&collisions[2], Collision::OverwritesInput { input, .. } if input.ends_with("d.mp4"))
+        );
+    }
+}
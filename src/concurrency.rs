@@ -0,0 +1,129 @@
+use crate::interrupt::Interrupt;
+use crate::load::Sample;
+use crate::progress::Progress;
+use crate::threads;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+/// how often --adaptive looks at the system's load
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(5);
+/// other processes using more than this share of the cpu make --adaptive run fewer files at a time
+const OTHERS_BUSY: f64 = 0.5;
+/// other processes using less than this share of the cpu let --adaptive run more files at a time again
+const OTHERS_QUIET: f64 = 0.25;
+/// less available memory than this share makes --adaptive run fewer files at a time
+const MEMORY_LOW: f64 = 0.1;
+/// more available memory than this share lets --adaptive run more files at a time again
+const MEMORY_PLENTY: f64 = 0.2;
+/// a load average above this many runnable threads per core while the cpu is never idle counts as overloaded
+const OVERLOAD_PER_CORE: f64 = 2.0;
+
+/// how many of the worker threads may start new files. threads with a higher number than the limit finish what
+/// they're doing and then wait until the limit goes up again. while paused, no thread starts a new file.
+pub struct Concurrency {
+    limit: AtomicUsize,
+    /// the number of worker threads, the most the limit can go up to
+    workers: AtomicUsize,
+    paused: AtomicBool,
+}
+
+impl Concurrency {
+    pub fn new(workers: usize) -> Self {
+        Concurrency {
+            limit: AtomicUsize::new(workers),
+            workers: AtomicUsize::new(workers),
+            paused: AtomicBool::new(false),
+        }
+    }
+
+    /// whether `thread` may pick up a new file right now
+    pub fn allows(&self, thread: usize) -> bool {
+        !self.paused() && thread < self.limit()
+    }
+
+    pub fn limit(&self) -> usize {
+        self.limit.load(Ordering::SeqCst)
+    }
+
+    pub fn workers(&self) -> usize {
+        self.workers.load(Ordering::SeqCst)
+    }
+
+    pub fn paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
+
+    /// sets the limit, kept between 1 and the number of worker threads. returns the new limit.
+    pub fn set_limit(&self, limit: usize) -> usize {
+        let limit = limit.clamp(1, self.workers());
+        self.limit.store(limit, Ordering::SeqCst);
+
+        limit
+    }
+
+    /// sets the number of worker threads, at least one, and lets all of them work. returns the new number.
+    pub fn set_workers(&self, workers: usize) -> usize {
+        let workers = workers.max(1);
+        self.workers.store(workers, Ordering::SeqCst);
+        self.limit.store(workers, Ordering::SeqCst);
+
+        workers
+    }
+
+    /// returns whether it was paused before
+    pub fn set_paused(&self, paused: bool) -> bool {
+        self.paused.swap(paused, Ordering::SeqCst)
+    }
+}
+
+/// lowers the limit by one every [`SAMPLE_INTERVAL`] while other processes need the cpu, memory runs low or the
+/// machine is overloaded, and raises it again once they're quiet. returns `false` if the load can't be read on this
+/// system, in which case nothing is adjusted.
+pub fn adapt(
+    concurrency: Arc<Concurrency>,
+    progress: Arc<Progress>,
+    interrupt: Arc<Interrupt>,
+) -> bool {
+    let Some(mut previous) = Sample::read(&interrupt.child_ids()) else {
+        return false;
+    };
+    let cores = threads::cores() as f64;
+
+    thread::spawn(move || loop {
+        thread::sleep(SAMPLE_INTERVAL);
+
+        let Some(sample) = Sample::read(&interrupt.child_ids()) else {
+            continue;
+        };
+        let usage = sample.usage_since(&previous);
+        let limit = concurrency.limit();
+
+        let change = if sample.memory_available < MEMORY_LOW {
+            Some((limit - 1, "memory is running low"))
+        } else if usage.others > OTHERS_BUSY {
+            Some((limit - 1, "other processes need the cpu"))
+        } else if usage.idle < 0.01 && sample.load_average > cores * OVERLOAD_PER_CORE {
+            Some((limit - 1, "the system is overloaded"))
+        } else if usage.others < OTHERS_QUIET
+            && sample.memory_available > MEMORY_PLENTY
+            && sample.load_average <= cores * OVERLOAD_PER_CORE
+        {
+            Some((limit + 1, "the system has capacity to spare"))
+        } else {
+            None
+        };
+
+        if let Some((wanted, reason)) = change {
+            let new_limit = concurrency.set_limit(wanted);
+            if new_limit != limit {
+                progress.concurrency_changed(new_limit, reason);
+            }
+        }
+
+        previous = sample;
+    });
+
+    true
+}
@@ -0,0 +1,373 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::iter::Peekable;
+use std::str::CharIndices;
+
+/// a parsed `--where` expression like `vcodec != 'hevc' && height >= 1080`. names are looked up like placeholders,
+/// values are compared as numbers if both sides are numbers and as text otherwise. a name without a value (e.g.
+/// `vcodec` for an audio file) isn't equal to anything, so only `!=` is true for it.
+///
+/// operators, from the loosest to the tightest binding: `||`, `&&`, `!`, then `==`, `!=`, `<`, `<=`, `>`, `>=`.
+/// parentheses group. a name on its own is true if it has a non-empty value.
+#[derive(Debug, Clone)]
+pub struct Condition {
+    expression: Expression,
+}
+
+#[derive(Debug, Clone)]
+enum Expression {
+    Or(Box<Expression>, Box<Expression>),
+    And(Box<Expression>, Box<Expression>),
+    Not(Box<Expression>),
+    Compare(Operand, Operator, Operand),
+    Present(Operand),
+}
+
+#[derive(Debug, Clone)]
+enum Operand {
+    Name(String),
+    Literal(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operator {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+#[derive(Debug)]
+pub struct ConditionError(String);
+
+impl Condition {
+    /// parses `condition`, failing on syntax errors and names for which `is_known` is false
+    pub fn parse(condition: &str, is_known: impl Fn(&str) -> bool) -> Result<Self, ConditionError> {
+        let mut parser = Parser {
+            chars: condition.char_indices().peekable(),
+            source: condition,
+            is_known: &is_known,
+        };
+        let expression = parser.or()?;
+
+        parser.skip_whitespace();
+        match parser.chars.peek() {
+            None => Ok(Condition { expression }),
+            Some((index, _)) => Err(ConditionError(format!(
+                "unexpected '{}'",
+                &condition[*index..]
+            ))),
+        }
+    }
+
+    /// `value` returns `None` for names that have no value for the current file
+    pub fn matches(&self, value: impl Fn(&str) -> Option<String>) -> bool {
+        self.expression.evaluate(&value)
+    }
+}
+
+impl Expression {
+    fn evaluate(&self, value: &impl Fn(&str) -> Option<String>) -> bool {
+        match self {
+            Expression::Or(left, right) => left.evaluate(value) || right.evaluate(value),
+            Expression::And(left, right) => left.evaluate(value) && right.evaluate(value),
+            Expression::Not(inner) => !inner.evaluate(value),
+            Expression::Present(operand) => {
+                operand.value(value).is_some_and(|value| !value.is_empty())
+            }
+            Expression::Compare(left, operator, right) => {
+                match (left.value(value), right.value(value)) {
+                    (Some(left), Some(right)) => operator.compare(&left, &right),
+                    _ => *operator == Operator::NotEqual,
+                }
+            }
+        }
+    }
+}
+
+impl Operand {
+    fn value(&self, value: &impl Fn(&str) -> Option<String>) -> Option<String> {
+        match self {
+            Operand::Name(name) => value(name),
+            Operand::Literal(literal) => Some(literal.clone()),
+        }
+    }
+}
+
+impl Operator {
+    fn compare(self, left: &str, right: &str) -> bool {
+        let ordering = match (left.parse::<f64>(), right.parse::<f64>()) {
+            (Ok(left), Ok(right)) => left.partial_cmp(&right),
+            _ => Some(left.cmp(right)),
+        };
+        let Some(ordering) = ordering else {
+            return self == Operator::NotEqual;
+        };
+
+        match self {
+            Operator::Equal => ordering.is_eq(),
+            Operator::NotEqual => ordering.is_ne(),
+            Operator::Less => ordering.is_lt(),
+            Operator::LessOrEqual => ordering.is_le(),
+            Operator::Greater => ordering.is_gt(),
+            Operator::GreaterOrEqual => ordering.is_ge(),
+        }
+    }
+}
+
+struct Parser<'a> {
+    chars: Peekable<CharIndices<'a>>,
+    source: &'a str,
+    is_known: &'a dyn Fn(&str) -> bool,
+}
+
+impl Parser<'_> {
+    fn or(&mut self) -> Result<Expression, ConditionError> {
+        let mut left = self.and()?;
+
+        while self.eat("||") {
+            left = Expression::Or(Box::new(left), Box::new(self.and()?));
+        }
+
+        Ok(left)
+    }
+
+    fn and(&mut self) -> Result<Expression, ConditionError> {
+        let mut left = self.not()?;
+
+        while self.eat("&&") {
+            left = Expression::And(Box::new(left), Box::new(self.not()?));
+        }
+
+        Ok(left)
+    }
+
+    fn not(&mut self) -> Result<Expression, ConditionError> {
+        self.skip_whitespace();
+
+        if self.rest().starts_with('!') && !self.rest().starts_with("!=") {
+            self.chars.next();
+            return Ok(Expression::Not(Box::new(self.not()?)));
+        }
+
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> Result<Expression, ConditionError> {
+        self.skip_whitespace();
+
+        if self.eat("(") {
+            let inner = self.or()?;
+            if !self.eat(")") {
+                return Err(self.unexpected("missing closing )"));
+            }
+
+            return Ok(inner);
+        }
+
+        let left = self.operand()?;
+
+        // longer operators first, so `<=` isn't read as `<`
+        let operators = [
+            ("==", Operator::Equal),
+            ("!=", Operator::NotEqual),
+            ("<=", Operator::LessOrEqual),
+            (">=", Operator::GreaterOrEqual),
+            ("<", Operator::Less),
+            (">", Operator::Greater),
+        ];
+
+        for (symbol, operator) in operators {
+            if self.eat(symbol) {
+                return Ok(Expression::Compare(left, operator, self.operand()?));
+            }
+        }
+
+        Ok(Expression::Present(left))
+    }
+
+    fn operand(&mut self) -> Result<Operand, ConditionError> {
+        self.skip_whitespace();
+
+        match self.chars.peek().copied() {
+            Some((_, quote @ ('\'' | '"'))) => {
+                self.chars.next();
+                let mut literal = String::new();
+
+                for (_, char) in self.chars.by_ref() {
+                    if char == quote {
+                        return Ok(Operand::Literal(literal));
+                    }
+                    literal.push(char);
+                }
+
+                Err(ConditionError(format!(
+                    "missing closing {quote} in '{}'",
+                    self.source
+                )))
+            }
+            Some((_, char)) if char.is_ascii_digit() || char == '-' || char == '.' => {
+                Ok(Operand::Literal(self.word()))
+            }
+            Some((_, char)) if char.is_alphabetic() => {
+                let name = self.word();
+                if !(self.is_known)(&name) {
+                    return Err(ConditionError(format!("unknown name '{name}'")));
+                }
+
+                Ok(Operand::Name(name))
+            }
+            _ => Err(self.unexpected("expected a name, number or quoted text")),
+        }
+    }
+
+    /// a name like `tag:artist` or a number like `29.97`
+    fn word(&mut self) -> String {
+        let mut word = String::new();
+
+        while let Some((_, char)) = self.chars.peek() {
+            if !(char.is_alphanumeric() || ['_', ':', '-', '.'].contains(char)) {
+                break;
+            }
+
+            word.push(*char);
+            self.chars.next();
+        }
+
+        word
+    }
+
+    /// consumes `symbol` if the expression continues with it
+    fn eat(&mut self, symbol: &str) -> bool {
+        self.skip_whitespace();
+
+        if !self.rest().starts_with(symbol) {
+            return false;
+        }
+
+        for _ in symbol.chars() {
+            self.chars.next();
+        }
+
+        true
+    }
+
+    fn rest(&mut self) -> &str {
+        match self.chars.peek() {
+            Some((index, _)) => &self.source[*index..],
+            None => "",
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some((_, char)) = self.chars.peek() {
+            if !char.is_whitespace() {
+                break;
+            }
+
+            self.chars.next();
+        }
+    }
+
+    fn unexpected(&mut self, expected: &str) -> ConditionError {
+        match self.rest() {
+            "" => ConditionError(format!("{expected} at the end")),
+            rest => ConditionError(format!("{expected} at '{rest}'")),
+        }
+    }
+}
+
+impl Display for ConditionError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Error for ConditionError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(condition: &str, values: &[(&str, &str)]) -> bool {
+        Condition::parse(condition, |_| true)
+            .unwrap()
+            .matches(|name| {
+                values
+                    .iter()
+                    .find(|(key, _)| *key == name)
+                    .map(|(_, value)| value.to_string())
+            })
+    }
+
+    fn error(condition: &str) -> String {
+        Condition::parse(condition, |name| name == "height")
+            .unwrap_err()
+            .to_string()
+    }
+
+    #[test]
+    fn documented_example() {
+        let condition = "vcodec != 'hevc' && height >= 1080";
+
+        assert!(matches(
+            condition,
+            &[("vcodec", "h264"), ("height", "1080")]
+        ));
+        assert!(!matches(
+            condition,
+            &[("vcodec", "hevc"), ("height", "2160")]
+        ));
+        assert!(!matches(
+            condition,
+            &[("vcodec", "h264"), ("height", "720")]
+        ));
+        // an audio file has no vcodec, which counts as not being hevc
+        assert!(matches(condition, &[("height", "1080")]));
+        assert!(!matches(condition, &[]));
+    }
+
+    #[test]
+    fn missing_values_only_satisfy_not_equal() {
+        for operator in ["==", "<", "<=", ">", ">="] {
+            assert!(!matches(&format!("width {operator} 0"), &[]));
+        }
+        assert!(matches("width != 0", &[]));
+        assert!(!matches("width", &[]));
+        assert!(!matches("tag:title", &[("tag:title", "")]));
+        assert!(matches("!tag:title", &[]));
+    }
+
+    #[test]
+    fn compares_numbers_as_numbers() {
+        assert!(matches("height > 720", &[("height", "1080")]));
+        assert!(matches("fps == 25", &[("fps", "25.0")]));
+        assert!(matches("duration < 60.5", &[("duration", "9")]));
+        // text is compared as text
+        assert!(matches("vcodec < 'vp9'", &[("vcodec", "h264")]));
+        assert!(!matches("vcodec == 'H264'", &[("vcodec", "h264")]));
+    }
+
+    #[test]
+    fn precedence_and_parentheses() {
+        let values = [("a", "1"), ("b", "0"), ("c", "1")];
+
+        assert!(matches("a == 1 || b == 1 && c == 0", &values));
+        assert!(!matches("(a == 1 || b == 1) && c == 0", &values));
+        assert!(matches("!b == 1 && !(c != 1)", &values));
+    }
+
+    #[test]
+    fn reports_mistakes() {
+        assert_eq!(error("vcodec == 'hevc'"), "unknown name 'vcodec'");
+        assert_eq!(error("height >= 'x"), "missing closing ' in 'height >= 'x'");
+        assert_eq!(error("(height > 1"), "missing closing ) at the end");
+        assert_eq!(error("height > 1 1"), "unexpected '1'");
+        assert_eq!(
+            error("height >"),
+            "expected a name, number or quoted text at the end"
+        );
+    }
+}
@@ -0,0 +1,164 @@
+use crate::worker::Workers;
+use serde_json::{json, Value};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// a unix socket that takes commands for a running batch, one per line, and answers each with a line of json. see
+/// [`command`] for what it understands. the socket file is removed again when this is dropped.
+pub struct Control {
+    path: PathBuf,
+}
+
+impl Control {
+    /// starts listening on `path` in the background. a socket left over from an earlier run that isn't listened on
+    /// anymore is replaced, anything else at `path` is left alone.
+    #[cfg(unix)]
+    pub fn listen(path: &Path, workers: Arc<Workers>) -> io::Result<Self> {
+        use std::io::{BufRead, BufReader, Write};
+        use std::os::unix::net::{UnixListener, UnixStream};
+        use std::thread;
+
+        let listener = match UnixListener::bind(path) {
+            Err(err)
+                if err.kind() == io::ErrorKind::AddrInUse && UnixStream::connect(path).is_err() =>
+            {
+                if !is_socket(path) {
+                    return Err(io::Error::new(
+                        io::ErrorKind::AlreadyExists,
+                        "the path exists and is not a socket",
+                    ));
+                }
+
+                std::fs::remove_file(path)?;
+                UnixListener::bind(path)?
+            }
+            result => result?,
+        };
+
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let workers = Arc::clone(&workers);
+
+                thread::spawn(move || {
+                    let Ok(mut writer) = stream.try_clone() else {
+                        return;
+                    };
+
+                    for line in BufReader::new(stream).lines() {
+                        let Ok(line) = line else {
+                            break;
+                        };
+                        if line.trim().is_empty() {
+                            continue;
+                        }
+
+                        let reply =
+                            command(&workers, &line).unwrap_or_else(|err| json!({ "error": err }));
+                        if writeln!(writer, "{reply}").is_err() {
+                            break;
+                        }
+                    }
+                });
+            }
+        });
+
+        Ok(Control {
+            path: path.to_path_buf(),
+        })
+    }
+
+    #[cfg(not(unix))]
+    pub fn listen(_path: &Path, _workers: Arc<Workers>) -> io::Result<Self> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "--control needs unix sockets",
+        ))
+    }
+}
+
+impl Drop for Control {
+    fn drop(&mut self) {
+        if is_socket(&self.path) {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+}
+
+/// whether `path` itself (not what it links to) is a unix socket
+#[cfg(unix)]
+fn is_socket(path: &Path) -> bool {
+    use std::os::unix::fs::FileTypeExt;
+
+    std::fs::symlink_metadata(path).is_ok_and(|metadata| metadata.file_type().is_socket())
+}
+
+#[cfg(not(unix))]
+fn is_socket(_path: &Path) -> bool {
+    false
+}
+
+/// runs a single command and returns the batch's status afterwards:
+///
+/// `pause` - running files are finished, but no new ones are started
+///
+/// `resume` - new files are started again
+///
+/// `workers N`, `workers +N`, `workers -N` - process N files at the same time, or N more or less than now, up to
+/// [`crate::worker::MAX_WORKERS`]
+///
+/// `status` - changes nothing
+fn command(workers: &Arc<Workers>, line: &str) -> Result<Value, String> {
+    let concurrency = &workers.concurrency;
+    let mut words = line.split_whitespace();
+
+    match (words.next(), words.next(), words.next()) {
+        (Some("pause"), None, _) => {
+            if !concurrency.set_paused(true) {
+                workers.progress.paused(true);
+            }
+        }
+        (Some("resume"), None, _) => {
+            if concurrency.set_paused(false) {
+                workers.progress.paused(false);
+            }
+        }
+        (Some("workers"), Some(count), None) => {
+            let current = concurrency.workers();
+            let wanted = match (count.strip_prefix('+'), count.strip_prefix('-')) {
+                (Some(more), _) => more.parse().map(|more: usize| current.saturating_add(more)),
+                (_, Some(less)) => less.parse().map(|less: usize| current.saturating_sub(less)),
+                _ => count.parse(),
+            }
+            .map_err(|_| format!("'{count}' is neither a number nor +N or -N"))?;
+
+            let count = workers.resize(wanted)?;
+            if count != current {
+                workers
+                    .progress
+                    .concurrency_changed(count, "as requested through --control");
+            }
+        }
+        (Some("status"), None, _) => {}
+        _ => {
+            return Err(format!(
+                "unknown command '{}', use pause, resume, workers N|+N|-N or status",
+                line.trim()
+            ))
+        }
+    }
+
+    let summary = workers.progress.summary();
+
+    Ok(json!({
+        "paused": concurrency.paused(),
+        "workers": concurrency.workers(),
+        "limit": concurrency.limit(),
+        "running": workers.running(),
+        "queued": workers.queued(),
+        "succeeded": summary.succeeded,
+        "failed": summary.failed,
+        "skipped": summary.skipped,
+        "elapsed": summary.elapsed.as_secs_f64(),
+    }))
+}
@@ -0,0 +1,210 @@
+use crate::interrupt::Interrupt;
+use std::ffi::{OsStr, OsString};
+use std::io::{BufRead, BufReader};
+#[cfg(unix)]
+use std::os::unix::process::CommandExt;
+#[cfg(windows)]
+use std::os::windows::process::CommandExt;
+use std::path::Path;
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+#[cfg(windows)]
+const CREATE_NEW_PROCESS_GROUP: u32 = 0x00000200;
+
+/// what's left of an ffmpeg run once the process has exited
+pub struct FfmpegOutput {
+    pub status: ExitStatus,
+    pub stderr: String,
+}
+
+/// whether `binary` (`ffmpeg` or `ffprobe`) can be started at all
+pub fn is_installed(binary: &str) -> bool {
+    Command::new(binary)
+        .arg("-version")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok()
+}
+
+/// the arguments ffmpeg is called with for a single file. `input_options` apply to the input and go before `-i`,
+/// `options` apply to the output.
+///
+/// `threads` limits how many threads ffmpeg uses for decoding, filtering and encoding (see --share-cores), unless
+/// the options already say so.
+pub fn args(
+    input: &Path,
+    input_options: &[OsString],
+    options: &[String],
+    output: &Path,
+    threads: Option<usize>,
+) -> Vec<OsString> {
+    let mut args: Vec<OsString> = ["-y", "-progress", "pipe:1", "-nostats"]
+        .into_iter()
+        .map(OsString::from)
+        .collect();
+
+    let in_input_options = |option| input_options.iter().any(|given| is_option(given, option));
+    let in_options = |option| {
+        options
+            .iter()
+            .any(|given| is_option(OsStr::new(given), option))
+    };
+    let limit = |option: &str, given: bool| -> Vec<OsString> {
+        match threads {
+            Some(threads) if !given => vec![option.into(), threads.to_string().into()],
+            _ => vec![],
+        }
+    };
+
+    // a global option, so it goes first
+    args.extend(limit(
+        "-filter_threads",
+        in_input_options("-filter_threads") || in_options("-filter_threads"),
+    ));
+    args.extend(input_options.iter().cloned());
+    // for decoding
+    args.extend(limit("-threads", in_input_options("-threads")));
+    args.push("-i".into());
+    args.push(input.into());
+    // for encoding
+    args.extend(limit("-threads", in_options("-threads")));
+    args.extend(options.iter().map(OsString::from));
+    args.push(output.into());
+
+    args
+}
+
+/// whether `argument` is `option`, with or without a stream specifier like `-threads:v`
+fn is_option(argument: &OsStr, option: &str) -> bool {
+    argument.to_str().is_some_and(|argument| {
+        argument == option
+            || argument
+                .strip_prefix(option)
+                .is_some_and(|rest| rest.starts_with(':'))
+    })
+}
+
+/// runs ffmpeg on a single file. whether `output` may be overwritten has been decided before (see `--on-exists`), so
+/// ffmpeg is never asked and doesn't get to read stdin.
+///
+/// calls `on_progress` with the processed media time and, once ffmpeg printed it, the input's duration. progress is
+/// read from `-progress pipe:1`, the duration from the `Duration:` line on stderr.
+///
+/// ffmpeg runs in its own process group so a Ctrl-C in the terminal only reaches ffzap, which decides what happens to
+/// the process through `interrupt`.
+pub fn run(
+    input: &Path,
+    input_options: &[OsString],
+    options: &[String],
+    output: &Path,
+    threads: Option<usize>,
+    interrupt: &Interrupt,
+    mut on_progress: impl FnMut(Duration, Option<Duration>),
+) -> std::io::Result<FfmpegOutput> {
+    let mut command = Command::new("ffmpeg");
+    command
+        .args(args(input, input_options, options, output, threads))
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+
+    #[cfg(unix)]
+    command.process_group(0);
+    #[cfg(windows)]
+    command.creation_flags(CREATE_NEW_PROCESS_GROUP);
+
+    let mut child = command.spawn()?;
+
+    // 0 means ffmpeg hasn't told us the duration (yet)
+    let duration_us = Arc::new(AtomicU64::new(0));
+
+    let stderr = child.stderr.take().expect("stderr is piped");
+    let stderr_handle = {
+        let duration_us = Arc::clone(&duration_us);
+
+        thread::spawn(move || {
+            let mut collected = String::new();
+
+            for line in BufReader::new(stderr).split(b'\n').map_while(Result::ok) {
+                let line = String::from_utf8_lossy(&line);
+
+                if duration_us.load(Ordering::Relaxed) == 0 {
+                    if let Some(duration) = parse_duration_line(&line) {
+                        duration_us.store(duration.as_micros() as u64, Ordering::Relaxed);
+                    }
+                }
+
+                collected.push_str(&line);
+                collected.push('\n');
+            }
+
+            collected
+        })
+    };
+
+    let stdout = child.stdout.take().expect("stdout is piped");
+
+    let child = Arc::new(Mutex::new(child));
+    interrupt.register(&child);
+
+    for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+        // older ffmpeg versions call it out_time_ms, but it's microseconds there as well
+        let value = line
+            .strip_prefix("out_time_us=")
+            .or_else(|| line.strip_prefix("out_time_ms="));
+
+        if let Some(Ok(micros)) = value.map(str::parse::<u64>) {
+            let duration = match duration_us.load(Ordering::Relaxed) {
+                0 => None,
+                total => Some(Duration::from_micros(total)),
+            };
+
+            on_progress(Duration::from_micros(micros), duration);
+        }
+    }
+
+    let status = wait(&child);
+    interrupt.unregister(&child);
+    let status = status?;
+    let stderr = stderr_handle.join().unwrap_or_default();
+
+    Ok(FfmpegOutput { status, stderr })
+}
+
+/// waits for `child` to exit without holding the lock, so `Interrupt` can still kill it in the meantime
+fn wait(child: &Mutex<Child>) -> std::io::Result<ExitStatus> {
+    loop {
+        if let Some(status) = child.lock().unwrap().try_wait()? {
+            return Ok(status);
+        }
+
+        thread::sleep(Duration::from_millis(50));
+    }
+}
+
+/// parses lines like `  Duration: 00:01:23.45, start: 0.000000, bitrate: 128 kb/s`
+fn parse_duration_line(line: &str) -> Option<Duration> {
+    let timestamp = line
+        .trim_start()
+        .strip_prefix("Duration: ")?
+        .split(',')
+        .next()?;
+    let mut parts = timestamp.split(':');
+
+    let hours = parts.next()?.parse::<f64>().ok()?;
+    let minutes = parts.next()?.parse::<f64>().ok()?;
+    let seconds = parts.next()?.parse::<f64>().ok()?;
+
+    let total = hours * 3600.0 + minutes * 60.0 + seconds;
+
+    if total > 0.0 {
+        Some(Duration::from_secs_f64(total))
+    } else {
+        None
+    }
+}
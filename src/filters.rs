@@ -0,0 +1,213 @@
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, TimeZone};
+use glob::{MatchOptions, Pattern};
+use regex::Regex;
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+/// which of the found files make it into the batch, see --exclude, --include-regex, --min-size, --max-size,
+/// --newer-than and --older-than
+pub struct InputFilter<'a> {
+    pub exclude: &'a [Pattern],
+    pub include: Option<&'a Regex>,
+    pub min_size: Option<u64>,
+    pub max_size: Option<u64>,
+    pub newer_than: Option<SystemTime>,
+    pub older_than: Option<SystemTime>,
+}
+
+impl InputFilter<'_> {
+    /// files whose size or modification time can't be read are kept, ffmpeg will report what's wrong with them
+    pub fn accepts(&self, path: &Path) -> bool {
+        if self.exclude.iter().any(|pattern| excludes(pattern, path)) {
+            return false;
+        }
+
+        if let Some(include) = self.include {
+            if !include.is_match(&path.to_string_lossy()) {
+                return false;
+            }
+        }
+
+        if self.min_size.is_none()
+            && self.max_size.is_none()
+            && self.newer_than.is_none()
+            && self.older_than.is_none()
+        {
+            return true;
+        }
+
+        let Ok(metadata) = fs::metadata(path) else {
+            return true;
+        };
+
+        let size = metadata.len();
+        let Ok(modified) = metadata.modified() else {
+            return true;
+        };
+
+        self.min_size.is_none_or(|min_size| size >= min_size)
+            && self.max_size.is_none_or(|max_size| size <= max_size)
+            && self
+                .newer_than
+                .is_none_or(|newer_than| modified > newer_than)
+            && self
+                .older_than
+                .is_none_or(|older_than| modified < older_than)
+    }
+}
+
+/// patterns without a `/` only look at the file name, like in a .gitignore, so `*_x264.*` excludes those files in
+/// every directory. others have to match the whole path.
+fn excludes(pattern: &Pattern, path: &Path) -> bool {
+    let options = MatchOptions {
+        require_literal_separator: true,
+        ..MatchOptions::new()
+    };
+
+    if pattern.as_str().contains('/') {
+        pattern.matches_path_with(path, options)
+    } else {
+        path.file_name()
+            .is_some_and(|name| pattern.matches_path_with(Path::new(name), options))
+    }
+}
+
+pub fn parse_glob(pattern: &str) -> Result<Pattern, String> {
+    Pattern::new(pattern).map_err(|err| err.msg.to_string())
+}
+
+pub fn parse_regex(regex: &str) -> Result<Regex, String> {
+    Regex::new(regex).map_err(|err| err.to_string())
+}
+
+/// a size like `500`, `700k`, `1.5G` or `2GiB`. units are powers of 1024.
+pub fn parse_size(size: &str) -> Result<u64, String> {
+    let size = size.trim();
+    let split = size
+        .find(|char: char| !char.is_ascii_digit() && char != '.')
+        .unwrap_or(size.len());
+    let (number, unit) = size.split_at(split);
+
+    let number = number
+        .parse::<f64>()
+        .map_err(|_| format!("'{size}' doesn't start with a number"))?;
+    let factor = match unit.trim().to_ascii_lowercase().as_str() {
+        "" | "b" => 1_u64,
+        "k" | "kb" | "kib" => 1 << 10,
+        "m" | "mb" | "mib" => 1 << 20,
+        "g" | "gb" | "gib" => 1 << 30,
+        "t" | "tb" | "tib" => 1 << 40,
+        unit => return Err(format!("unknown unit '{unit}', use k, M, G or T")),
+    };
+
+    Ok((number * factor as f64) as u64)
+}
+
+/// a point in time like `2024-05-31`, `2024-05-31T18:00:00` (both local time), an RFC 3339 timestamp, or an age like
+/// `90m`, `12h`, `7d` or `2w` that is counted back from now
+pub fn parse_time(time: &str) -> Result<SystemTime, String> {
+    let time = time.trim();
+
+    if let Some(age) = parse_age(time) {
+        return SystemTime::now()
+            .checked_sub(age)
+            .ok_or_else(|| format!("'{time}' is too long ago"));
+    }
+
+    if let Ok(date_time) = DateTime::parse_from_rfc3339(time) {
+        return Ok(date_time.into());
+    }
+
+    let local = NaiveDateTime::parse_from_str(time, "%Y-%m-%dT%H:%M:%S")
+        .or_else(|_| NaiveDateTime::parse_from_str(time, "%Y-%m-%d %H:%M:%S"))
+        .or_else(|_| {
+            NaiveDate::parse_from_str(time, "%Y-%m-%d")
+                .map(|date| date.and_hms_opt(0, 0, 0).expect("midnight exists"))
+        })
+        .map_err(|_| format!("'{time}' is neither a date like 2024-05-31 nor an age like 7d"))?;
+
+    Local
+        .from_local_datetime(&local)
+        .earliest()
+        .map(SystemTime::from)
+        .ok_or_else(|| format!("'{time}' doesn't exist in the local time zone"))
+}
+
+fn parse_age(age: &str) -> Option<Duration> {
+    let unit_start = age
+        .len()
+        .checked_sub(1)
+        .filter(|index| age.is_char_boundary(*index))?;
+    let (number, unit) = age.split_at(unit_start);
+    let number = number.parse::<u64>().ok()?;
+    let seconds = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => return None,
+    };
+
+    Some(Duration::from_secs(number.checked_mul(seconds)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn excluded(pattern: &str, path: &str) -> bool {
+        excludes(&parse_glob(pattern).unwrap(), Path::new(path))
+    }
+
+    #[test]
+    fn sizes() {
+        assert_eq!(parse_size("500"), Ok(500));
+        assert_eq!(parse_size("700k"), Ok(700 * 1024));
+        assert_eq!(parse_size("1.5G"), Ok(1536 * 1024 * 1024));
+        assert_eq!(parse_size("2GiB"), Ok(2 << 30));
+        assert_eq!(parse_size(" 1 MB "), Ok(1 << 20));
+        assert!(parse_size("k").is_err());
+        assert!(parse_size("5x").is_err());
+    }
+
+    #[test]
+    fn ages_count_back_from_now() {
+        let week = Duration::from_secs(7 * 24 * 60 * 60);
+        let seven_days_ago = parse_time("7d").unwrap();
+        let elapsed = SystemTime::now().duration_since(seven_days_ago).unwrap();
+
+        assert!(elapsed >= week && elapsed < week + Duration::from_secs(60));
+        assert_eq!(parse_age("90m"), Some(Duration::from_secs(90 * 60)));
+        assert_eq!(parse_age("2w"), Some(week * 2));
+        assert_eq!(parse_age("7"), None);
+        assert_eq!(parse_age("7ü"), None);
+        assert_eq!(parse_age(""), None);
+    }
+
+    #[test]
+    fn dates() {
+        let rfc3339 = parse_time("2024-05-31T18:00:00Z").unwrap();
+        assert_eq!(
+            rfc3339.duration_since(SystemTime::UNIX_EPOCH).unwrap(),
+            Duration::from_secs(1_717_178_400)
+        );
+
+        let date = parse_time("2024-05-31").unwrap();
+        let date_time = parse_time("2024-05-31 18:00:00").unwrap();
+        assert_eq!(
+            date_time.duration_since(date).unwrap(),
+            Duration::from_secs(18 * 60 * 60)
+        );
+        assert!(parse_time("yesterday").is_err());
+    }
+
+    #[test]
+    fn patterns_without_a_slash_match_the_file_name() {
+        assert!(excluded("*_x264.*", "vids/a/clip_x264.mp4"));
+        assert!(!excluded("*_x264.*", "vids/a_x264.d/clip.mp4"));
+        assert!(excluded("**/sample/**", "vids/sample/clip.mp4"));
+        assert!(!excluded("vids/*.mp4", "vids/a/clip.mp4"));
+    }
+}
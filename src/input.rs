@@ -0,0 +1,163 @@
+use crate::lists::{read_input_list, read_path_list};
+use glob::glob;
+use std::ffi::OsStr;
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Component, Path, PathBuf};
+
+/// the file extensions directories given to `-i` are searched for, unless --extensions says otherwise
+pub const DEFAULT_EXTENSIONS: &str =
+    "mp4,mkv,mov,avi,webm,m4v,mpg,mpeg,ts,mts,m2ts,wmv,flv,3gp,mp3,flac,wav,m4a,aac,ogg,opus,wma";
+
+/// a file to process
+#[derive(Debug, Clone)]
+pub struct Input {
+    pub path: PathBuf,
+    /// 1-based position among all files of the batch, before any of them are skipped
+    pub index: usize,
+    /// the directory {{reldir}} starts below, if the file was found in one
+    pub base: Option<PathBuf>,
+}
+
+/// a file found by one of the `-i` arguments, before it gets its index
+pub struct Found {
+    pub path: PathBuf,
+    pub base: Option<PathBuf>,
+}
+
+/// finds the files for a single `-i` argument. that can be
+///
+/// - `-` to read paths from stdin, separated by null bytes if there are any (like `find -print0` writes them) or
+///   by newlines otherwise
+/// - `@list.txt` to read paths from a list like --input-list does
+/// - a directory, which is searched recursively for files with one of `extensions`
+/// - a glob pattern
+pub fn find(argument: &str, extensions: &[String]) -> Result<Vec<Found>, String> {
+    let without_base = |paths: Vec<PathBuf>| {
+        paths
+            .into_iter()
+            .map(|path| Found { path, base: None })
+            .collect()
+    };
+
+    if argument == "-" {
+        let mut paths = vec![];
+        io::stdin()
+            .read_to_end(&mut paths)
+            .map_err(|err| format!("Could not read paths from stdin: {err}"))?;
+
+        // file names like `#1 hit.mp3` are common, and programs piping paths in don't write comments
+        let separator = if paths.contains(&0) { b'\0' } else { b'\n' };
+        return Ok(without_base(read_path_list(&paths, separator, false)));
+    }
+
+    if let Some(list) = argument.strip_prefix('@') {
+        return read_input_list(Path::new(list))
+            .map(without_base)
+            .map_err(|err| format!("Could not read input list {list}: {err}"));
+    }
+
+    let directory = Path::new(argument);
+    if directory.is_dir() {
+        let mut paths = vec![];
+        walk(directory, extensions, &mut paths)
+            .map_err(|err| format!("Could not read directory {argument}: {err}"))?;
+
+        return Ok(paths
+            .into_iter()
+            .map(|path| Found {
+                path,
+                base: Some(directory.to_path_buf()),
+            })
+            .collect());
+    }
+
+    let paths = glob(argument).map_err(|err| format!("Invalid glob {argument}: {}", err.msg))?;
+    let base = glob_base(argument);
+
+    Ok(paths
+        .filter_map(Result::ok)
+        .map(|path| Found {
+            path,
+            base: Some(base.clone()),
+        })
+        .collect())
+}
+
+/// collects the files below `directory` with one of `extensions` in alphabetical order, like glob would. symlinks to
+/// files are followed, symlinks to directories aren't so loops can't happen. subdirectories that can't be read are
+/// skipped.
+fn walk(directory: &Path, extensions: &[String], paths: &mut Vec<PathBuf>) -> io::Result<()> {
+    let mut entries = fs::read_dir(directory)?
+        .filter_map(Result::ok)
+        .collect::<Vec<_>>();
+    entries.sort_by_key(|entry| entry.file_name());
+
+    for entry in entries {
+        let path = entry.path();
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+
+        if file_type.is_dir() {
+            let _ = walk(&path, extensions, paths);
+        } else if path.is_file() && has_extension(&path, extensions) {
+            paths.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+fn has_extension(path: &Path, extensions: &[String]) -> bool {
+    path.extension()
+        .and_then(OsStr::to_str)
+        .is_some_and(|extension| {
+            extensions.iter().any(|wanted| {
+                wanted
+                    .trim_start_matches('.')
+                    .eq_ignore_ascii_case(extension)
+            })
+        })
+}
+
+/// the literal directory a glob pattern starts in, e.g. `vids` for `vids/**/*.mp4`. the last component is always
+/// treated as the file name pattern, even if it doesn't contain a wildcard. a leading `./` is dropped, just like glob
+/// drops it from the paths it finds.
+pub fn glob_base(pattern: &str) -> PathBuf {
+    let components = Path::new(pattern).components().collect::<Vec<Component>>();
+
+    components[..components.len().saturating_sub(1)]
+        .iter()
+        .take_while(|component| {
+            !component
+                .as_os_str()
+                .to_string_lossy()
+                .contains(['*', '?', '['])
+        })
+        .filter(|component| **component != Component::CurDir)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn base_is_the_literal_start_of_the_pattern() {
+        assert_eq!(glob_base("vids/**/*.mp4"), Path::new("vids"));
+        assert_eq!(glob_base("./vids/2024-*/*.mp4"), Path::new("vids"));
+        assert_eq!(glob_base("/media/vids/*.mkv"), Path::new("/media/vids"));
+        assert_eq!(glob_base("vids/a/clip.mp4"), Path::new("vids/a"));
+        assert_eq!(glob_base("*.mp4"), Path::new(""));
+    }
+
+    #[test]
+    fn extensions_ignore_case() {
+        let extensions = ["mp4".to_string()];
+
+        assert!(has_extension(Path::new("a/clip.MP4"), &extensions));
+        assert!(!has_extension(Path::new("a/clip.mkv"), &extensions));
+        assert!(!has_extension(Path::new("a/mp4"), &extensions));
+    }
+}
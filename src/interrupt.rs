@@ -0,0 +1,78 @@
+use crate::progress::Progress;
+use std::collections::HashMap;
+use std::process::Child;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// keeps track of Ctrl-C presses. the first one stops threads from picking up new files and lets the running ffmpeg
+/// processes finish, the second one kills them.
+pub struct Interrupt {
+    count: AtomicUsize,
+    /// running ffmpeg processes by pid
+    children: Mutex<HashMap<u32, Arc<Mutex<Child>>>>,
+}
+
+impl Interrupt {
+    /// installs the Ctrl-C handler. can only be called once.
+    pub fn install(progress: Arc<Progress>) -> Result<Arc<Self>, ctrlc::Error> {
+        let interrupt = Arc::new(Interrupt {
+            count: AtomicUsize::new(0),
+            children: Mutex::new(HashMap::new()),
+        });
+
+        let handler_interrupt = Arc::clone(&interrupt);
+        ctrlc::set_handler(
+            move || match handler_interrupt.count.fetch_add(1, Ordering::SeqCst) {
+                0 => progress.eprintln(
+                    "Stopping once the running files are done. Press Ctrl-C again to abort them."
+                        .to_string(),
+                ),
+                1 => {
+                    progress.eprintln("Aborting the running files...".to_string());
+                    handler_interrupt.kill_children();
+                }
+                _ => {}
+            },
+        )?;
+
+        Ok(interrupt)
+    }
+
+    /// no new files should be started
+    pub fn stopping(&self) -> bool {
+        self.count.load(Ordering::SeqCst) >= 1
+    }
+
+    /// running files are being aborted
+    pub fn aborted(&self) -> bool {
+        self.count.load(Ordering::SeqCst) >= 2
+    }
+
+    /// makes `child` get killed on the second Ctrl-C
+    pub fn register(&self, child: &Arc<Mutex<Child>>) {
+        let id = child.lock().unwrap().id();
+        self.children.lock().unwrap().insert(id, Arc::clone(child));
+
+        // Ctrl-C might have been pressed the second time right before the child was registered
+        if self.aborted() {
+            let _ = child.lock().unwrap().kill();
+        }
+    }
+
+    pub fn unregister(&self, child: &Arc<Mutex<Child>>) {
+        let id = child.lock().unwrap().id();
+        self.children.lock().unwrap().remove(&id);
+    }
+
+    /// pids of the running ffmpeg processes
+    pub fn child_ids(&self) -> Vec<u32> {
+        self.children.lock().unwrap().keys().copied().collect()
+    }
+
+    fn kill_children(&self) {
+        for child in self.children.lock().unwrap().values() {
+            // fails if the process exited in the meantime, which is fine
+            let _ = child.lock().unwrap().kill();
+        }
+    }
+}
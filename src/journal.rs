@@ -0,0 +1,230 @@
+use crate::lists::{path_from_bytes, path_to_bytes};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JobState {
+    Pending,
+    Running,
+    Done,
+    Failed,
+}
+
+/// the file given with `--journal`. every state change of every input is appended as a json line, so a batch that
+/// was interrupted (e.g. by a reboot) can be resumed by running ffzap again with the same journal. inputs that are
+/// done with the same options are skipped, everything else is processed (again).
+pub struct Journal {
+    file: Mutex<File>,
+    options_hash: String,
+    /// the last known entry for each input, from previous runs
+    previous: HashMap<PathBuf, Entry>,
+}
+
+struct Entry {
+    state: JobState,
+    options_hash: String,
+}
+
+impl JobState {
+    fn as_str(self) -> &'static str {
+        match self {
+            JobState::Pending => "pending",
+            JobState::Running => "running",
+            JobState::Done => "done",
+            JobState::Failed => "failed",
+        }
+    }
+
+    fn parse(state: &str) -> Option<Self> {
+        match state {
+            "pending" => Some(JobState::Pending),
+            "running" => Some(JobState::Running),
+            "done" => Some(JobState::Done),
+            "failed" => Some(JobState::Failed),
+            _ => None,
+        }
+    }
+}
+
+impl Journal {
+    /// opens the journal, creating it if necessary. `options` is everything that influences the output of a job, an
+    /// input only counts as done if it was processed with the same options.
+    pub fn open(path: &Path, options: &str) -> io::Result<Self> {
+        let mut previous = HashMap::new();
+        let mut latest_lines = HashMap::new();
+        let mut order = vec![];
+
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(err) => return Err(err),
+        };
+
+        // a crash can leave a half written last line behind, which is simply ignored
+        for line in content.lines() {
+            let Ok(value) = serde_json::from_str::<Value>(line) else {
+                continue;
+            };
+            let (Some(input), Some(state), Some(options_hash)) = (
+                read_path(&value, "input"),
+                value["state"].as_str().and_then(JobState::parse),
+                value["options_hash"].as_str(),
+            ) else {
+                continue;
+            };
+
+            if !latest_lines.contains_key(&input) {
+                order.push(input.clone());
+            }
+            latest_lines.insert(input.clone(), line.to_string());
+            previous.insert(
+                input,
+                Entry {
+                    state,
+                    options_hash: options_hash.to_string(),
+                },
+            );
+        }
+
+        // only keep the latest line per input so the journal doesn't grow with every run. written next to it and
+        // moved over it, so a crash in between leaves the old journal intact
+        let mut compacted_name = OsString::from(".");
+        compacted_name.push(path.file_name().unwrap_or_default());
+        compacted_name.push(".ffzap-compact");
+        let compacted_path = path.with_file_name(compacted_name);
+
+        let mut compacted = File::create(&compacted_path)?;
+        for input in &order {
+            writeln!(compacted, "{}", latest_lines[input])?;
+        }
+        compacted.sync_all()?;
+        drop(compacted);
+        fs::rename(&compacted_path, path)?;
+
+        Ok(Journal {
+            file: Mutex::new(OpenOptions::new().append(true).open(path)?),
+            options_hash: hash(options),
+            previous,
+        })
+    }
+
+    /// whether a previous run already processed `input` successfully with the same options
+    pub fn is_done(&self, input: &Path) -> bool {
+        self.previous.get(input).is_some_and(|entry| {
+            entry.state == JobState::Done && entry.options_hash == self.options_hash
+        })
+    }
+
+    pub fn record(&self, input: &Path, state: JobState, output: Option<&Path>) -> io::Result<()> {
+        let mut line = Map::new();
+        write_path(&mut line, "input", Some(input));
+        line.insert("state".to_string(), state.as_str().into());
+        write_path(&mut line, "output", output);
+        line.insert("options_hash".to_string(), self.options_hash.clone().into());
+        let line = Value::Object(line);
+
+        let mut file = self.file.lock().unwrap();
+        writeln!(file, "{line}")?;
+        file.flush()
+    }
+}
+
+/// stores `path` under `key`. paths that aren't valid unicode are stored under `{key}_hex` instead, as the hex of their
+/// raw bytes on unix, so two of them never end up as the same entry.
+fn write_path(line: &mut Map<String, Value>, key: &str, path: Option<&Path>) {
+    match path.map(|path| (path, path.to_str())) {
+        None => {
+            line.insert(key.to_string(), Value::Null);
+        }
+        Some((_, Some(path))) => {
+            line.insert(key.to_string(), path.into());
+        }
+        Some((path, None)) => {
+            let hex = path_to_bytes(path)
+                .iter()
+                .map(|byte| format!("{byte:02x}"))
+                .collect::<String>();
+            line.insert(format!("{key}_hex"), hex.into());
+        }
+    }
+}
+
+/// the path stored under `key` by [`write_path`]
+fn read_path(line: &Value, key: &str) -> Option<PathBuf> {
+    if let Some(path) = line[key].as_str() {
+        return Some(PathBuf::from(path));
+    }
+
+    let hex = line[format!("{key}_hex")].as_str()?;
+    let bytes = (0..hex.len())
+        .step_by(2)
+        .map(|start| u8::from_str_radix(hex.get(start..start + 2)?, 16).ok())
+        .collect::<Option<Vec<u8>>>()?;
+
+    Some(path_from_bytes(&bytes))
+}
+
+/// 64 bit FNV-1a. the std hashers aren't guaranteed to be stable between rust versions, this one has to be because
+/// it's persisted in the journal.
+fn hash(options: &str) -> String {
+    let hash = options.bytes().fold(0xcbf29ce484222325_u64, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3)
+    });
+
+    format!("{hash:016x}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(path: Option<&Path>) -> (Value, Option<PathBuf>) {
+        let mut line = Map::new();
+        write_path(&mut line, "input", path);
+        let line = Value::Object(line);
+        let read = read_path(&line, "input");
+
+        (line, read)
+    }
+
+    #[test]
+    fn unicode_paths_are_written_as_strings() {
+        let path = Path::new("vids/Café #1.mp4");
+        let (line, read) = round_trip(Some(path));
+
+        assert_eq!(line["input"], "vids/Café #1.mp4");
+        assert_eq!(read.as_deref(), Some(path));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn other_paths_are_written_as_hex() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let path = Path::new(OsStr::from_bytes(b"vids/caf\xe9.mp4"));
+        let (line, read) = round_trip(Some(path));
+
+        assert_eq!(line["input_hex"], "766964732f636166e92e6d7034");
+        assert!(line.get("input").is_none());
+        assert_eq!(read.as_deref(), Some(path));
+    }
+
+    #[test]
+    fn missing_and_broken_paths_are_none() {
+        assert_eq!(round_trip(None).1, None);
+        assert_eq!(
+            read_path(&serde_json::json!({ "input_hex": "7g" }), "input"),
+            None
+        );
+        assert_eq!(
+            read_path(&serde_json::json!({ "input_hex": "616" }), "input"),
+            None
+        );
+    }
+}
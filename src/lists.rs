@@ -0,0 +1,84 @@
+use crate::progress::{tail, FAILED_LIST_STDERR_LINES};
+use std::ffi::OsStr;
+use std::fs::{self, File};
+use std::io::{self, Write};
+#[cfg(unix)]
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// reads the files to process from a list with one path per line. empty lines and lines starting with `#` are
+/// ignored, so a list written with `--failed-list` can be fed back in as is.
+pub fn read_input_list(path: &Path) -> io::Result<Vec<PathBuf>> {
+    Ok(read_path_list(&fs::read(path)?, b'\n', true))
+}
+
+/// splits `list` into paths at `separator`, skipping empty entries and, with `comments`, entries starting with `#`. a
+/// trailing `\r` is dropped from lines, so lists written on windows work too.
+pub fn read_path_list(list: &[u8], separator: u8, comments: bool) -> Vec<PathBuf> {
+    list.split(|byte| *byte == separator)
+        .map(|line| line.strip_suffix(b"\r").unwrap_or(line))
+        .filter(|line| !line.trim_ascii().is_empty())
+        .filter(|line| !comments || !line.starts_with(b"#"))
+        .map(path_from_bytes)
+        .collect()
+}
+
+/// the file given with `--failed-list`. every failed input is written as its own line, preceded by `#` comments
+/// with the exit status and the end of ffmpeg's error output.
+pub struct FailedList {
+    file: Mutex<File>,
+}
+
+impl FailedList {
+    pub fn create(path: &Path) -> io::Result<Self> {
+        Ok(FailedList {
+            file: Mutex::new(File::create(path)?),
+        })
+    }
+
+    /// `exit_code` is `None` if ffmpeg couldn't be started or was killed by a signal
+    pub fn record(&self, input: &Path, exit_code: Option<i32>, error: &str) -> io::Result<()> {
+        let mut entry = match exit_code {
+            Some(code) => format!("# exit code {code}\n"),
+            None => "# no exit code\n".to_string(),
+        };
+        for line in tail(error, FAILED_LIST_STDERR_LINES).lines() {
+            entry.push_str(&format!("# {line}\n"));
+        }
+        let mut entry = entry.into_bytes();
+        // names like `#1 hit.mp4` would be read back as a comment
+        if input.as_os_str().as_encoded_bytes().starts_with(b"#") {
+            entry.extend_from_slice(b"./");
+        }
+        entry.extend_from_slice(&path_to_bytes(input));
+        entry.push(b'\n');
+
+        // written in one go so entries of different threads don't interleave
+        let mut file = self.file.lock().unwrap();
+        file.write_all(&entry)?;
+        file.flush()
+    }
+}
+
+/// paths are written byte for byte on unix, so file names that aren't valid unicode survive a round trip through
+/// the lists
+#[cfg(unix)]
+pub fn path_to_bytes(path: &Path) -> Vec<u8> {
+    path.as_os_str().as_bytes().to_vec()
+}
+
+#[cfg(not(unix))]
+pub fn path_to_bytes(path: &Path) -> Vec<u8> {
+    path.to_string_lossy().into_owned().into_bytes()
+}
+
+#[cfg(unix)]
+pub fn path_from_bytes(bytes: &[u8]) -> PathBuf {
+    PathBuf::from(OsStr::from_bytes(bytes))
+}
+
+#[cfg(not(unix))]
+pub fn path_from_bytes(bytes: &[u8]) -> PathBuf {
+    PathBuf::from(OsStr::new(&*String::from_utf8_lossy(bytes)))
+}
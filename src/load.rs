@@ -0,0 +1,112 @@
+use std::collections::HashMap;
+use std::fs;
+
+/// a snapshot of the system's counters in /proc. only available on linux, everywhere else reading fails.
+pub struct Sample {
+    /// cpu time of all cores since boot, in clock ticks
+    total: u64,
+    /// the part of `total` that was spent idle or waiting for io
+    idle: u64,
+    /// cpu time used so far by each of the given processes
+    processes: HashMap<u32, u64>,
+    /// load average of the last minute
+    pub load_average: f64,
+    /// share of the memory that is still available, 0 to 1
+    pub memory_available: f64,
+}
+
+/// what happened between two samples
+pub struct Usage {
+    /// share of the cpu time that was idle, 0 to 1
+    pub idle: f64,
+    /// share of the cpu time used by processes other than the given ones, 0 to 1
+    pub others: f64,
+}
+
+impl Sample {
+    /// reads the counters, including the cpu time used by `pids` so far. `None` if /proc can't be read.
+    pub fn read(pids: &[u32]) -> Option<Self> {
+        let stat = fs::read_to_string("/proc/stat").ok()?;
+        // user nice system idle iowait irq softirq steal, guest time is already part of user
+        let cpu = stat
+            .lines()
+            .next()?
+            .strip_prefix("cpu ")?
+            .split_whitespace()
+            .take(8)
+            .map(|value| value.parse::<u64>().ok())
+            .collect::<Option<Vec<u64>>>()?;
+        if cpu.len() < 5 {
+            return None;
+        }
+
+        let load_average = fs::read_to_string("/proc/loadavg")
+            .ok()?
+            .split_whitespace()
+            .next()?
+            .parse()
+            .ok()?;
+
+        let meminfo = fs::read_to_string("/proc/meminfo").ok()?;
+        let memory = |key: &str| {
+            meminfo
+                .lines()
+                .find_map(|line| line.strip_prefix(key))?
+                .trim_start_matches(':')
+                .split_whitespace()
+                .next()?
+                .parse::<f64>()
+                .ok()
+        };
+        let memory_available = memory("MemAvailable")? / memory("MemTotal")?;
+
+        Some(Sample {
+            total: cpu.iter().sum(),
+            idle: cpu[3] + cpu[4],
+            processes: pids
+                .iter()
+                .filter_map(|pid| Some((*pid, process_time(*pid)?)))
+                .collect(),
+            load_average,
+            memory_available,
+        })
+    }
+
+    /// the cpu usage since `earlier`
+    pub fn usage_since(&self, earlier: &Sample) -> Usage {
+        let total = self.total.saturating_sub(earlier.total).max(1) as f64;
+        let idle = self.idle.saturating_sub(earlier.idle) as f64;
+
+        // processes that started in between used all of their time in between. processes that ended in between are
+        // missing, so others are overestimated a bit right after a file is done.
+        let ours = self
+            .processes
+            .iter()
+            .map(|(pid, time)| {
+                time.saturating_sub(earlier.processes.get(pid).copied().unwrap_or(0))
+            })
+            .sum::<u64>() as f64;
+
+        Usage {
+            idle: (idle / total).clamp(0.0, 1.0),
+            others: ((total - idle - ours) / total).clamp(0.0, 1.0),
+        }
+    }
+}
+
+/// user and system time of a process, in clock ticks
+fn process_time(pid: u32) -> Option<u64> {
+    let stat = fs::read_to_string(format!("/proc/{pid}/stat")).ok()?;
+    // the process name in parentheses can contain spaces, the fields after it can't
+    let fields = stat
+        .rsplit_once(')')?
+        .1
+        .split_whitespace()
+        .collect::<Vec<&str>>();
+
+    // utime and stime are the 14th and 15th field, counting the pid and name
+    let utime = fields.get(11)?.parse::<u64>().ok()?;
+    let stime = fields.get(12)?.parse::<u64>().ok()?;
+
+    Some(utime + stime)
+}
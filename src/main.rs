@@ -1,26 +1,181 @@
+mod collisions;
+mod concurrency;
+mod condition;
+mod control;
+mod ffmpeg;
+mod filters;
+mod input;
+mod interrupt;
+mod journal;
+mod lists;
+mod load;
+mod on_exists;
+mod order;
+mod placeholders;
+mod plan;
+mod probe;
+mod progress;
+mod temp_output;
+mod template;
+mod threads;
+mod worker;
+
+use crate::collisions::OnCollision;
+use crate::concurrency::Concurrency;
+use crate::condition::Condition;
+use crate::control::Control;
+use crate::filters::InputFilter;
+use crate::input::{Found, Input};
+use crate::interrupt::Interrupt;
+use crate::journal::{JobState, Journal};
+use crate::lists::{read_input_list, FailedList};
+use crate::on_exists::OnExists;
+use crate::order::Order;
+use crate::placeholders::{Batch, FileInfo, Needs};
+use crate::plan::Job;
+use crate::progress::{OutputFormat, Progress};
+use crate::template::Template;
+use crate::threads::Threads;
+use crate::worker::{Settings, Workers};
+use chrono::Local;
 use clap::Parser;
-use glob::glob;
-use std::ffi::OsStr;
-use std::fs::create_dir_all;
-use std::path::{Path, PathBuf};
-use std::process::{Command, Stdio};
-use std::sync::{Arc, Mutex};
-use std::thread;
+use serde_json::json;
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::SystemTime;
+
+/// at least one file failed, but not all of them
+const EXIT_SOME_FAILED: i32 = 3;
+/// every single file failed
+const EXIT_ALL_FAILED: i32 = 4;
+/// same as a shell reports for unknown commands
+const EXIT_FFMPEG_NOT_FOUND: i32 = 127;
+/// 128 + SIGINT, like shells report processes stopped by Ctrl-C
+const EXIT_INTERRUPTED: i32 = 130;
 
 #[derive(Parser, Debug, Clone)]
 #[command(version, about)]
 struct CmdArgs {
     /// the amount of threads you want to utilize. most systems can handle 2. go higher if you have a powerful computer.
-    #[arg(short, long, default_value_t = 2)]
-    thread_count: u8,
+    /// `auto` uses one per available core (respecting container cpu limits), a percentage like `50%` a share of them
+    #[arg(short, long, visible_alias = "threads", default_value = "2", value_parser = Threads::parse)]
+    thread_count: Threads,
+
+    /// adjust how many files are processed at the same time to the system's load (linux only). starts with
+    /// --thread-count files, runs fewer while other programs need the cpu or memory runs low, and goes back up to
+    /// --thread-count once they're done
+    #[arg(long)]
+    adaptive: bool,
+
+    /// split the available cores evenly between the ffmpeg processes running at the same time, by passing `-threads`
+    /// and `-filter_threads` to each of them, unless they are already part of the options
+    #[arg(long)]
+    share_cores: bool,
+
+    /// listen on this unix socket for commands that change the running batch, one per line: `pause`, `resume`,
+    /// `workers N` (or `+N`/`-N`) to process more or fewer files at the same time, and `status`. every command is
+    /// answered with the batch's status as json
+    #[arg(long, value_name = "SOCKET")]
+    control: Option<PathBuf>,
 
-    /// options you want to pass to ffmpeg. for the output file name, use --output
+    /// options you want to pass to ffmpeg, split like a shell would, so quotes and escapes work as usual. for the
+    /// output file name, use --output
     #[arg(short, long, allow_hyphen_values = true)]
-    ffmpeg_options: String,
+    ffmpeg_options: Option<String>,
 
-    /// the directory with all files you want to process. supports unix globs
-    #[arg(short, long)]
-    input_directory: String,
+    /// options you want to pass to ffmpeg, given after a `--` separator. they are passed exactly as they are, so no
+    /// extra quoting is needed. combined with --ffmpeg-options, they come after those
+    #[arg(last = true, value_name = "FFMPEG_OPTIONS")]
+    ffmpeg_args: Vec<String>,
+
+    /// options for ffmpeg that apply to the input file and therefore have to go before `-i`, like `-ss` or `-f`.
+    /// split like --ffmpeg-options and supports the same placeholders as --output
+    #[arg(long, allow_hyphen_values = true)]
+    input_options: Option<String>,
+
+    /// the files you want to process. can be given multiple times and takes a unix glob, a directory that is
+    /// searched recursively for files with one of --extensions, `@list.txt` to read a list like --input-list does, or
+    /// `-` to read paths from stdin, separated by newlines or null bytes (e.g. from `find -print0`)
+    #[arg(short, long, required_unless_present = "input_list")]
+    input_directory: Vec<String>,
+
+    /// read the files to process from a text file with one path per line, in addition to --input-directory.
+    /// lines starting with # are ignored, so a list written by --failed-list can be used directly
+    #[arg(long)]
+    input_list: Option<PathBuf>,
+
+    /// the file extensions to look for when --input-directory is a directory
+    #[arg(long, value_delimiter = ',', default_value = input::DEFAULT_EXTENSIONS)]
+    extensions: Vec<String>,
+
+    /// leave out files matching this glob. can be given multiple times. patterns without a `/` only have to match the
+    /// file name, e.g. `*_x264.*`, others the whole path, e.g. `**/sample/**`
+    #[arg(long, value_parser = filters::parse_glob)]
+    exclude: Vec<glob::Pattern>,
+
+    /// only process files whose path matches this regular expression
+    #[arg(long, value_parser = filters::parse_regex)]
+    include_regex: Option<regex::Regex>,
+
+    /// only process files at least this big, e.g. `700k` or `1.5G`
+    #[arg(long, value_parser = filters::parse_size)]
+    min_size: Option<u64>,
+
+    /// only process files at most this big, e.g. `700k` or `1.5G`
+    #[arg(long, value_parser = filters::parse_size)]
+    max_size: Option<u64>,
+
+    /// only process files modified after this date (e.g. `2024-05-31`) or within this time (e.g. `7d` or `12h`)
+    #[arg(long, value_parser = filters::parse_time)]
+    newer_than: Option<SystemTime>,
+
+    /// only process files modified before this date (e.g. `2024-05-31`) or longer ago than this (e.g. `7d` or `12h`)
+    #[arg(long, value_parser = filters::parse_time)]
+    older_than: Option<SystemTime>,
+
+    /// only process files for which this expression over ffprobe's data is true, e.g.
+    /// `vcodec != 'hevc' && height >= 1080` or `duration > 60`. takes the names of all placeholders that use ffprobe,
+    /// the operators == != < <= > >= && || ! and parentheses
+    #[arg(long = "where", value_name = "EXPRESSION")]
+    where_condition: Option<String>,
+
+    /// in which order the files are processed
+    #[arg(long, value_enum, default_value_t = Order::LargestFirst)]
+    order: Order,
+
+    /// write every file that failed to this file, together with ffmpeg's exit code and the end of its error output.
+    /// pass it to --input-list to retry just the failed files
+    #[arg(long)]
+    failed_list: Option<PathBuf>,
+
+    /// keep track of every file's state in this file. if a batch gets interrupted, run the same command again with
+    /// the same journal and files that are already done are skipped
+    #[arg(long)]
+    journal: Option<PathBuf>,
+
+    /// what to do if an output file already exists
+    #[arg(long, value_enum, default_value_t = OnExists::Fail)]
+    on_exists: OnExists,
+
+    /// skip files whose output already exists and is newer than the input, like make does. outputs older than their
+    /// input are handled according to --on-exists
+    #[arg(long)]
+    incremental: bool,
+
+    /// what to do if several files would be written to the same output, or an output is one of the inputs. checked
+    /// for the whole batch before anything is processed
+    #[arg(long, value_enum, default_value_t = OnCollision::Abort)]
+    on_collision: OnCollision,
+
+    /// added before the extension of colliding outputs with --on-collision suffix. {{n}} is replaced with a number
+    /// that counts up until the name is free
+    #[arg(long, default_value = "_{{n}}")]
+    collision_suffix: String,
+
+    /// only print the ffmpeg command and output path for every file without running anything or creating directories
+    #[arg(long)]
+    dry_run: bool,
 
     /// Specify the output file pattern. Use placeholders to customize file paths:
     ///
@@ -30,109 +185,406 @@ struct CmdArgs {
     ///
     /// {{ext}}  - Original file's extension
     ///
+    /// {{parent}} - Name of the directory the original file is in
+    ///
+    /// {{reldir}} - Original file's directory structure, starting below the directory or the part of the glob before the first wildcard it was found with
+    ///
+    /// {{index}} - Position of the file in the batch, zero-padded to the number of files. {{index:5}} pads to 5 digits
+    ///
+    /// {{date}}, {{time}} - When the batch was started. Takes a strftime format like {{date:%Y%m%d}}
+    ///
+    /// {{mtime}} - When the original file was last modified. Takes a strftime format like {{mtime:%Y}}
+    ///
+    /// Arguments after the : can contain spaces, like {{date:%d %b %Y}}, and can be quoted, like {{date:'%d %b %Y'}}
+    ///
+    /// {{hash}} - Start of the original file's content hash
+    ///
+    /// {{width}}, {{height}}, {{duration}} (seconds), {{vcodec}}, {{acodec}}, {{bitrate}} (kb/s), {{fps}} - Properties of the original file, read with ffprobe
+    ///
+    /// {{tag:artist}}, {{tag:album}}, {{tag:track}}, ... - Any metadata tag of the original file, read with ffprobe
+    ///
     /// Example: /destination/{{dir}}/{{name}}_transcoded.{{ext}}
     ///
     /// Outputs the file in /destination, mirroring the original structure and keeping both the file extension and name, while adding _transcoded to the name.
+    ///
+    /// Placeholders can be passed through filters, e.g. {{name|lower|replace:' ':'_'}}:
+    ///
+    /// lower, upper - Changes the case
+    ///
+    /// replace:'from':'to' - Replaces every occurrence of 'from' with 'to'
+    ///
+    /// slug - Only keeps lowercase letters and digits, everything else becomes a single -
+    ///
+    /// truncate:n - Keeps the first n characters
+    ///
+    /// default:'value' - Used if the placeholder is empty, e.g. for files without an extension
+    ///
+    /// For literal braces, write {{'{{'}} and {{'}}'}}.
     #[arg(short, long)]
     output: String,
-    // {{ext}} -> extension, {{name}} filename without extension, {{dir}} -> directory structure from starting point to file, {{parent}} -> parent directory of starting point
+
+    /// how to report progress. `human` shows progress bars (or plain lines when not run in a terminal), `json` prints
+    /// one json event per line to stdout for other tools to consume
+    #[arg(long, value_enum, default_value_t = OutputFormat::Human)]
+    output_format: OutputFormat,
 }
 
 fn main() {
     let cmd_args = CmdArgs::parse();
 
-    let paths = Arc::new(Mutex::new(match glob(&cmd_args.input_directory) {
-        Ok(paths) => paths.filter_map(Result::ok).collect::<Vec<PathBuf>>(),
-        Err(err) => {
-            eprintln!("{}", err.msg);
-            std::process::exit(1);
+    if !cmd_args.dry_run && !ffmpeg::is_installed("ffmpeg") {
+        eprintln!(
+            "Could not run ffmpeg. Please check if it's correctly installed and in your PATH."
+        );
+        std::process::exit(EXIT_FFMPEG_NOT_FOUND);
+    }
+
+    let thread_count = cmd_args.thread_count.count();
+    let ffmpeg_threads = cmd_args
+        .share_cores
+        .then(|| (threads::cores() / thread_count).max(1));
+
+    let mut found = vec![];
+    for argument in &cmd_args.input_directory {
+        match input::find(argument, &cmd_args.extensions) {
+            Ok(files) => {
+                if files.is_empty() && argument.starts_with('@') {
+                    eprintln!("Input list {} doesn't contain any files", &argument[1..]);
+                }
+                found.extend(files)
+            }
+            Err(err) => {
+                eprintln!("{err}");
+                std::process::exit(1);
+            }
         }
-    }));
-
-    let mut thread_handles = vec![];
-
-    for thread in 0..cmd_args.thread_count {
-        let paths: Arc<Mutex<Vec<PathBuf>>> = Arc::clone(&paths);
-        let args = cmd_args.clone();
-
-        let handle = thread::spawn(move || loop {
-            let path_to_process = {
-                let mut queue = paths.lock().unwrap();
-
-                queue.pop()
-            };
-
-            match path_to_process {
-                Some(path) => {
-                    println!("[THREAD {thread}] -- Processing {}", path.display());
-                    let split_options = &mut args.ffmpeg_options.split(' ').collect::<Vec<&str>>();
-
-                    let mut final_file_name = args
-                        .output
-                        .replace("{{ext}}", path.extension().unwrap().to_str().unwrap());
-                    final_file_name = final_file_name
-                        .replace("{{name}}", &path.file_stem().unwrap().to_str().unwrap());
-                    final_file_name = final_file_name.replace(
-                        "{{dir}}",
-                        &path.parent().unwrap_or(Path::new("")).to_str().unwrap(),
-                    );
-                    final_file_name = final_file_name.replace(
-                        "{{parent}}",
-                        &path
-                            .parent()
-                            .unwrap_or(Path::new(""))
-                            .file_name()
-                            .unwrap_or(OsStr::new(""))
-                            .to_str()
-                            .unwrap_or(""),
+    }
+    if let Some(input_list) = &cmd_args.input_list {
+        match read_input_list(input_list) {
+            Ok(paths) => {
+                if paths.is_empty() {
+                    eprintln!(
+                        "Input list {} doesn't contain any files",
+                        input_list.display()
                     );
-                    let final_path_parent = Path::new(&final_file_name).parent().unwrap();
-
-                    if !final_path_parent.exists() {
-                        match create_dir_all(final_path_parent) {
-                            Ok(_) => {}
-                            Err(err) => {
-                                eprintln!(
-                                    "[THREAD {thread}] -- Could not create directory structure for file {}",
-                                    final_file_name
-                                );
-                                eprintln!("{}", err)
-                            }
-                        }
-                    }
-
-                    if let Ok(output) = Command::new("ffmpeg")
-                        .args(["-i", path.to_str().unwrap()])
-                        .args(split_options)
-                        .arg(&final_file_name)
-                        .stdout(Stdio::piped())
-                        .stderr(Stdio::piped())
-                        .output()
-                    {
-                        if output.status.success() {
-                            println!("[THREAD {thread}] -- Success, saving to {final_file_name}");
-                        } else {
-                            eprintln!("[THREAD {thread}] -- Error!");
-                            eprintln!(
-                                "[THREAD {thread}] -- Error is: {}",
-                                String::from_utf8_lossy(&output.stderr)
-                            );
-                            eprintln!("[THREAD {thread}] -- Continuing with next task if there's more to do...");
-                        }
-                    } else {
-                        eprintln!("[THREAD {thread}] -- There was an error running ffmpeg. Please check if it's correctly installed and working as intended.");
-                    }
                 }
-                None => {
-                    break;
+                found.extend(paths.into_iter().map(|path| Found { path, base: None }))
+            }
+            Err(err) => {
+                eprintln!("Could not read input list {}: {err}", input_list.display());
+                std::process::exit(1);
+            }
+        }
+    }
+
+    // the same file can easily be found by several arguments, e.g. a directory and a glob inside it
+    let mut seen = HashSet::new();
+    found.retain(|found| seen.insert(collisions::identity(&found.path)));
+
+    let filter = InputFilter {
+        exclude: &cmd_args.exclude,
+        include: cmd_args.include_regex.as_ref(),
+        min_size: cmd_args.min_size,
+        max_size: cmd_args.max_size,
+        newer_than: cmd_args.newer_than,
+        older_than: cmd_args.older_than,
+    };
+    found.retain(|found| filter.accepts(&found.path));
+
+    let ffmpeg_options = match ffmpeg_options(&cmd_args) {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("Could not parse --ffmpeg-options: {err}");
+            std::process::exit(1);
+        }
+    };
+    let input_options = match cmd_args.input_options.as_deref().map(shell_words::split) {
+        Some(Ok(options)) => options,
+        Some(Err(err)) => {
+            eprintln!("Could not parse --input-options: {err}");
+            std::process::exit(1);
+        }
+        None => vec![],
+    };
+
+    let output_template =
+        Template::parse(&cmd_args.output, placeholders::is_known).unwrap_or_else(|err| {
+            eprintln!("Invalid --output: {err}");
+            std::process::exit(1);
+        });
+    let input_option_templates = input_options
+        .iter()
+        .map(|option| Template::parse(option, placeholders::is_known))
+        .collect::<Result<Vec<Template>, _>>()
+        .unwrap_or_else(|err| {
+            eprintln!("Invalid --input-options: {err}");
+            std::process::exit(1);
+        });
+
+    let condition = cmd_args.where_condition.as_ref().map(|condition| {
+        Condition::parse(condition, placeholders::needs_probe).unwrap_or_else(|err| {
+            eprintln!("Invalid --where: {err}");
+            std::process::exit(1);
+        })
+    });
+
+    let mut needs = Needs::of(std::iter::once(&output_template).chain(&input_option_templates));
+    needs.probe |= condition.is_some() || cmd_args.order == Order::LongestDurationFirst;
+
+    if needs.probe && !ffmpeg::is_installed("ffprobe") {
+        eprintln!("The placeholders or --where you used need ffprobe, but it could not be run. Please check if it's correctly installed and in your PATH.");
+        std::process::exit(EXIT_FFMPEG_NOT_FOUND);
+    }
+
+    let collision_suffix = Template::parse(&cmd_args.collision_suffix, |name| name == "n")
+        .ok()
+        .filter(|suffix| suffix.uses(|name| name == "n"))
+        .unwrap_or_else(|| {
+            eprintln!(
+                "Invalid --collision-suffix: it has to contain {{{{n}}}} and no other placeholders"
+            );
+            std::process::exit(1);
+        });
+
+    let paths = found
+        .iter()
+        .map(|found| found.path.clone())
+        .collect::<Vec<PathBuf>>();
+    let infos = plan::gather(&paths, needs, thread_count);
+
+    // files that couldn't be probed stay in, so they are reported as failed instead of silently left out
+    let (found, infos): (Vec<Found>, Vec<_>) = found
+        .into_iter()
+        .zip(infos)
+        .filter(|(_, info)| match (&condition, info) {
+            (Some(condition), Ok(info)) => condition.matches(|name| info.probe_value(name)),
+            _ => true,
+        })
+        .unzip();
+
+    let inputs = found
+        .into_iter()
+        .enumerate()
+        .map(|(index, found)| Input {
+            path: found.path,
+            index: index + 1,
+            base: found.base,
+        })
+        .collect::<Vec<Input>>();
+
+    let batch = Batch {
+        file_count: inputs.len(),
+        started_at: Local::now(),
+    };
+
+    let durations = infos
+        .iter()
+        .map(|info| info.as_ref().ok().and_then(FileInfo::duration))
+        .collect::<Vec<_>>();
+
+    let mut jobs = plan::resolve(
+        inputs,
+        infos,
+        &output_template,
+        &input_option_templates,
+        &batch,
+    );
+
+    let collisions = collisions::find(&jobs);
+    if !collisions.is_empty() {
+        match cmd_args.on_collision {
+            OnCollision::Abort => {
+                eprintln!("Some outputs collide, so files would overwrite each other:");
+                for collision in &collisions {
+                    eprintln!("{collision}");
                 }
+                eprintln!("Change --output so every file gets a name of its own, or pass --on-collision suffix to number them.");
+                std::process::exit(1);
+            }
+            OnCollision::Suffix => collisions::disambiguate(&mut jobs, &collision_suffix),
+        }
+    }
+
+    // sorted only now so collisions are numbered in the order the files were found, no matter the --order
+    let mut jobs = order::sort(jobs, durations, cmd_args.order);
+
+    if cmd_args.dry_run {
+        dry_run(&cmd_args, &ffmpeg_options, ffmpeg_threads, &jobs);
+        return;
+    }
+
+    // created after reading the input list, which might be the very same file
+    let failed_list = cmd_args.failed_list.as_ref().map(|path| {
+        FailedList::create(path).unwrap_or_else(|err| {
+            eprintln!("Could not create failed list {}: {err}", path.display());
+            std::process::exit(1);
+        })
+    });
+
+    let journal = cmd_args.journal.as_ref().map(|path| {
+        let options = format!(
+            "{}\n{}\n{}",
+            shell_words::join(&input_options),
+            shell_words::join(&ffmpeg_options),
+            cmd_args.output
+        );
+
+        Journal::open(path, &options).unwrap_or_else(|err| {
+            eprintln!("Could not open journal {}: {err}", path.display());
+            std::process::exit(1);
+        })
+    });
+
+    let progress = Arc::new(Progress::new(
+        cmd_args.output_format,
+        thread_count,
+        jobs.len(),
+    ));
+
+    let interrupt = Interrupt::install(Arc::clone(&progress)).unwrap_or_else(|err| {
+        eprintln!("Could not set up the Ctrl-C handler: {err}");
+        std::process::exit(1);
+    });
+
+    if let Some(journal) = &journal {
+        jobs.retain(|job| {
+            let done = journal.is_done(&job.input.path);
+            if done {
+                progress.skipped(&job.input.path, "already done according to the journal");
             }
+
+            !done
         });
+    }
+
+    for job in &jobs {
+        progress.queued(&job.input.path);
+
+        if let Some(journal) = &journal {
+            if let Err(err) = journal.record(&job.input.path, JobState::Pending, None) {
+                eprintln!("Could not update the journal: {err}");
+            }
+        }
+    }
+
+    let concurrency = Arc::new(Concurrency::new(thread_count));
+    if cmd_args.adaptive
+        && !concurrency::adapt(
+            Arc::clone(&concurrency),
+            Arc::clone(&progress),
+            Arc::clone(&interrupt),
+        )
+    {
+        progress.eprintln(
+            "Could not read the system's load, --adaptive only works on linux. Ignoring it."
+                .to_string(),
+        );
+    }
+
+    let workers = Arc::new(Workers::new(
+        jobs,
+        concurrency,
+        Arc::clone(&progress),
+        Arc::clone(&interrupt),
+        failed_list,
+        journal,
+        Settings {
+            ffmpeg_options,
+            on_exists: cmd_args.on_exists,
+            incremental: cmd_args.incremental,
+            share_cores: cmd_args.share_cores,
+        },
+    ));
+
+    let control = cmd_args.control.as_ref().map(|path| {
+        Control::listen(path, Arc::clone(&workers)).unwrap_or_else(|err| {
+            eprintln!("Could not listen on {}: {err}", path.display());
+            std::process::exit(1);
+        })
+    });
+
+    if let Err(err) = workers.spawn(thread_count) {
+        if workers.spawned() == 0 {
+            eprintln!("Could not start any threads: {err}");
+            std::process::exit(1);
+        }
 
-        thread_handles.push(handle);
+        let started = workers.concurrency.set_workers(workers.spawned());
+        progress.eprintln(format!(
+            "Could only start {started} of {thread_count} threads: {err}"
+        ));
     }
+    workers.join();
+    drop(control);
 
-    for handle in thread_handles {
-        handle.join().unwrap();
+    let summary = progress.finish();
+
+    if interrupt.stopping() {
+        std::process::exit(EXIT_INTERRUPTED);
+    }
+
+    if summary.failed > 0 {
+        if summary.failed == summary.total() - summary.skipped {
+            std::process::exit(EXIT_ALL_FAILED);
+        }
+
+        std::process::exit(EXIT_SOME_FAILED);
+    }
+}
+
+/// all options for ffmpeg, from both --ffmpeg-options and after `--`
+fn ffmpeg_options(cmd_args: &CmdArgs) -> Result<Vec<String>, shell_words::ParseError> {
+    let mut options = match &cmd_args.ffmpeg_options {
+        Some(options) => shell_words::split(options)?,
+        None => vec![],
+    };
+    options.extend(cmd_args.ffmpeg_args.iter().cloned());
+
+    Ok(options)
+}
+
+/// prints what would be run for each file without touching anything
+fn dry_run(
+    cmd_args: &CmdArgs,
+    ffmpeg_options: &[String],
+    ffmpeg_threads: Option<usize>,
+    jobs: &[Job],
+) {
+    for job in jobs {
+        let path = &job.input.path;
+        let plan = match &job.plan {
+            Ok(plan) => plan,
+            Err(error) => {
+                eprintln!("# {}: {error}", path.display());
+                continue;
+            }
+        };
+
+        let args = ffmpeg::args(
+            path,
+            &plan.input_options,
+            ffmpeg_options,
+            &plan.output,
+            ffmpeg_threads,
+        );
+        let command = std::iter::once("ffmpeg".into())
+            .chain(args.iter().map(|arg| arg.to_string_lossy()))
+            .collect::<Vec<_>>();
+
+        match cmd_args.output_format {
+            OutputFormat::Human => {
+                println!("# {} -> {}", path.display(), plan.output.display());
+                println!("{}", shell_words::join(command));
+            }
+            OutputFormat::Json => println!(
+                "{}",
+                json!({
+                    "event": "planned",
+                    "input": path.to_string_lossy(),
+                    "output": plan.output.to_string_lossy(),
+                    "command": command,
+                })
+            ),
+        }
     }
 }
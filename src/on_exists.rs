@@ -0,0 +1,118 @@
+use clap::ValueEnum;
+use std::fs::{self, OpenOptions};
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// what to do if a file's output already exists
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OnExists {
+    /// leave the existing file alone and don't process the input
+    Skip,
+    /// replace the existing file
+    Overwrite,
+    /// write to a new file instead, e.g. `clip (1).mp4`
+    Rename,
+    /// count the input as failed
+    Fail,
+}
+
+pub enum Decision {
+    /// process the input and write to `output`. with `rename`, the finished file is moved to a free name instead if
+    /// `output` exists by then, see [`move_to_free_name`]
+    Write { output: PathBuf, rename: bool },
+    /// don't process the input, for the given reason
+    Skip(String),
+    /// count the input as failed with the given error
+    Fail(String),
+}
+
+/// decides whether and where ffmpeg gets to write `output`, before it is started. with `incremental`, outputs that are
+/// newer than their input are skipped like `make` would, older ones are handled by `policy`.
+pub fn decide(input: &Path, output: &Path, policy: OnExists, incremental: bool) -> Decision {
+    let Ok(output_metadata) = fs::metadata(output) else {
+        return Decision::Write {
+            output: output.to_path_buf(),
+            rename: false,
+        };
+    };
+
+    if incremental {
+        let output_modified = output_metadata.modified();
+        let input_modified = fs::metadata(input).and_then(|metadata| metadata.modified());
+
+        if let (Ok(output_modified), Ok(input_modified)) = (output_modified, input_modified) {
+            if output_modified >= input_modified {
+                return Decision::Skip(format!("{} is up to date", output.display()));
+            }
+        }
+    }
+
+    match policy {
+        OnExists::Skip => Decision::Skip(format!("{} already exists", output.display())),
+        OnExists::Overwrite => Decision::Write {
+            output: output.to_path_buf(),
+            rename: false,
+        },
+        // the free name is only picked once ffmpeg is done, so nothing at the final name can look like a finished
+        // output before it is one, even if ffzap gets killed
+        OnExists::Rename => Decision::Write {
+            output: output.to_path_buf(),
+            rename: true,
+        },
+        OnExists::Fail => Decision::Fail(format!("{} already exists", output.display())),
+    }
+}
+
+/// moves `file` to the first free name of `output`, `output (1)`, `output (2)`, ... and returns it. never replaces
+/// anything, even if other threads or programs pick names at the same time.
+pub fn move_to_free_name(file: &Path, output: &Path) -> io::Result<PathBuf> {
+    for number in 0.. {
+        let candidate = numbered(output, number);
+
+        // a hard link only succeeds if nothing is there yet
+        match fs::hard_link(file, &candidate) {
+            Ok(()) => {
+                // only the temporary name is left behind if this fails, the output is complete either way
+                let _ = fs::remove_file(file);
+                return Ok(candidate);
+            }
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists => continue,
+            // e.g. filesystems without hard links, claim the name with an empty file right before moving there
+            Err(_) => match OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&candidate)
+            {
+                Ok(_) => {
+                    return match fs::rename(file, &candidate) {
+                        Ok(()) => Ok(candidate),
+                        Err(err) => {
+                            let _ = fs::remove_file(&candidate);
+                            Err(err)
+                        }
+                    }
+                }
+                Err(err) if err.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(err) => return Err(err),
+            },
+        }
+    }
+
+    unreachable!("there's always a free name")
+}
+
+/// `output` itself for 0, otherwise `name (number).ext`
+fn numbered(output: &Path, number: usize) -> PathBuf {
+    if number == 0 {
+        return output.to_path_buf();
+    }
+
+    let mut file_name = output.file_stem().unwrap_or_default().to_os_string();
+    file_name.push(format!(" ({number})"));
+    if let Some(extension) = output.extension() {
+        file_name.push(".");
+        file_name.push(extension);
+    }
+
+    output.with_file_name(file_name)
+}
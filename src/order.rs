@@ -0,0 +1,132 @@
+use crate::plan::Job;
+use clap::ValueEnum;
+use rand::seq::SliceRandom;
+use std::cmp::{Ordering, Reverse};
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+/// in which order the files are processed, see [`sort`]
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Order {
+    /// biggest files first, so no thread is left with a huge file at the very end
+    LargestFirst,
+    /// smallest files first, to get as many files done as early as possible
+    SmallestFirst,
+    /// longest files first according to ffprobe, like largest-first but better for mixed bitrates
+    LongestDurationFirst,
+    /// by path, with numbers compared by their value, so `ep2` comes before `ep10`
+    Name,
+    /// least recently modified files first
+    Mtime,
+    /// shuffled
+    Random,
+}
+
+/// sorts `jobs` into the order they are processed in. `durations` are the inputs' durations, in the same order as
+/// `jobs`, only needed for [`Order::LongestDurationFirst`]. files whose size, duration or modification time isn't
+/// known are processed last, otherwise ties keep their order.
+pub fn sort(jobs: Vec<Job>, durations: Vec<Option<Duration>>, order: Order) -> Vec<Job> {
+    let mut pairs = jobs.into_iter().zip(durations).collect::<Vec<_>>();
+    let size = |job: &Job| {
+        fs::metadata(&job.input.path)
+            .ok()
+            .map(|metadata| metadata.len())
+    };
+
+    match order {
+        Order::LargestFirst => pairs.sort_by_cached_key(|(job, _)| Reverse(size(job))),
+        Order::SmallestFirst => pairs.sort_by_cached_key(|(job, _)| {
+            let size = size(job);
+            (size.is_none(), size)
+        }),
+        Order::LongestDurationFirst => pairs.sort_by_key(|(_, duration)| Reverse(*duration)),
+        Order::Name => pairs.sort_by(|(a, _), (b, _)| natural_cmp(&a.input.path, &b.input.path)),
+        Order::Mtime => pairs.sort_by_cached_key(|(job, _)| {
+            let modified = fs::metadata(&job.input.path)
+                .and_then(|metadata| metadata.modified())
+                .ok();
+
+            (modified.is_none(), modified)
+        }),
+        Order::Random => pairs.shuffle(&mut rand::thread_rng()),
+    }
+
+    pairs.into_iter().map(|(job, _)| job).collect()
+}
+
+/// compares paths like a human would, with runs of digits compared by their value
+fn natural_cmp(a: &Path, b: &Path) -> Ordering {
+    let (a, b) = (a.to_string_lossy(), b.to_string_lossy());
+    let (mut a, mut b) = (a.chars().peekable(), b.chars().peekable());
+
+    loop {
+        match (a.peek().copied(), b.peek().copied()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let number = |chars: &mut std::iter::Peekable<std::str::Chars>| {
+                    let mut digits = String::new();
+                    while let Some(digit) = chars.next_if(char::is_ascii_digit) {
+                        digits.push(digit);
+                    }
+                    digits
+                };
+                let (x, y) = (number(&mut a), number(&mut b));
+                let (x_value, y_value) = (x.trim_start_matches('0'), y.trim_start_matches('0'));
+
+                let ordering = x_value
+                    .len()
+                    .cmp(&y_value.len())
+                    .then_with(|| x_value.cmp(y_value))
+                    .then_with(|| x.len().cmp(&y.len()));
+                if ordering.is_ne() {
+                    return ordering;
+                }
+            }
+            (Some(x), Some(y)) => {
+                let ordering = x.cmp(&y);
+                if ordering.is_ne() {
+                    return ordering;
+                }
+                a.next();
+                b.next();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cmp(a: &str, b: &str) -> Ordering {
+        natural_cmp(Path::new(a), Path::new(b))
+    }
+
+    #[test]
+    fn numbers_are_compared_by_value() {
+        assert_eq!(cmp("ep2.mp4", "ep10.mp4"), Ordering::Less);
+        assert_eq!(cmp("ep10.mp4", "ep9.mp4"), Ordering::Greater);
+        assert_eq!(cmp("s1/ep10.mp4", "s2/ep1.mp4"), Ordering::Less);
+        assert_eq!(
+            cmp("99999999999999999999999", "100000000000000000000000"),
+            Ordering::Less
+        );
+    }
+
+    #[test]
+    fn leading_zeros_only_break_ties() {
+        assert_eq!(cmp("ep007.mp4", "ep10.mp4"), Ordering::Less);
+        assert_eq!(cmp("ep07.mp4", "ep7.mp4"), Ordering::Greater);
+        assert_eq!(cmp("ep7.mp4", "ep7.mp4"), Ordering::Equal);
+    }
+
+    #[test]
+    fn text_is_compared_char_by_char() {
+        assert_eq!(cmp("a.mp4", "b.mp4"), Ordering::Less);
+        assert_eq!(cmp("ep", "ep1"), Ordering::Less);
+        assert_eq!(cmp("ep1a", "ep1"), Ordering::Greater);
+    }
+}
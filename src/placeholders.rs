@@ -0,0 +1,326 @@
+use crate::input::Input;
+use crate::probe::Probe;
+use crate::template::Template;
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Local};
+use std::ffi::OsString;
+use std::fmt::Write;
+use std::fs::{self, File};
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use std::time::Duration;
+
+/// every placeholder that can be used in templates like --output
+const NAMES: [&str; 10] = [
+    "ext", "name", "dir", "parent", "reldir", "index", "date", "time", "mtime", "hash",
+];
+
+/// placeholders that need ffprobe to look at the file first, in addition to every `tag:...`
+const PROBE_NAMES: [&str; 7] = [
+    "width", "height", "duration", "vcodec", "acodec", "bitrate", "fps",
+];
+
+/// formats used by {{date}}, {{time}} and {{mtime}} if none is given. no colons, windows doesn't allow them in paths.
+const DATE_FORMAT: &str = "%Y-%m-%d";
+const TIME_FORMAT: &str = "%H-%M-%S";
+const MTIME_FORMAT: &str = "%Y-%m-%d_%H-%M-%S";
+
+/// how many hex digits of the content hash {{hash}} uses
+const HASH_LENGTH: usize = 8;
+/// the most digits {{index}} can be padded to
+const MAX_INDEX_WIDTH: usize = 64;
+
+pub fn is_known(name: &str) -> bool {
+    if NAMES.contains(&name) || needs_probe(name) {
+        return true;
+    }
+
+    match name.split_once(':') {
+        Some(("index", width)) => width
+            .parse::<usize>()
+            .is_ok_and(|width| width <= MAX_INDEX_WIDTH),
+        Some(("date" | "time" | "mtime", format)) => is_valid_format(format),
+        _ => false,
+    }
+}
+
+/// whether ffprobe has to be run to get a value for the placeholder
+pub fn needs_probe(name: &str) -> bool {
+    PROBE_NAMES.contains(&name) || name.strip_prefix("tag:").is_some_and(|tag| !tag.is_empty())
+}
+
+/// whether the input file has to be read completely to get a value for the placeholder
+pub fn needs_hash(name: &str) -> bool {
+    name == "hash"
+}
+
+/// some specifiers can only be parsed, not formatted (like `%#z`), so the format is tried out once
+fn is_valid_format(format: &str) -> bool {
+    !format.is_empty()
+        && StrftimeItems::new(format).all(|item| item != Item::Error)
+        && format_time(&Local::now(), format).is_some()
+}
+
+/// `None` if chrono can't format `time` with `format`
+fn format_time(time: &DateTime<Local>, format: &str) -> Option<String> {
+    let mut formatted = String::new();
+    write!(formatted, "{}", time.format(format)).ok()?;
+
+    Some(formatted)
+}
+
+/// everything placeholders need that's the same for all files of a batch
+#[derive(Debug, Clone)]
+pub struct Batch {
+    /// how many files the batch has, {{index}} is padded to the same number of digits
+    pub file_count: usize,
+    pub started_at: DateTime<Local>,
+}
+
+/// which of the placeholders that are expensive to fill the templates use
+#[derive(Debug, Clone, Copy)]
+pub struct Needs {
+    pub probe: bool,
+    pub hash: bool,
+}
+
+impl Needs {
+    pub fn of<'a>(templates: impl IntoIterator<Item = &'a Template> + Clone) -> Self {
+        Needs {
+            probe: templates
+                .clone()
+                .into_iter()
+                .any(|template| template.uses(needs_probe)),
+            hash: templates
+                .into_iter()
+                .any(|template| template.uses(needs_hash)),
+        }
+    }
+}
+
+/// what has to be read from a file before its placeholders can be filled. only what the templates need is gathered.
+#[derive(Default)]
+pub struct FileInfo {
+    probe: Option<Probe>,
+    hash: Option<String>,
+}
+
+impl FileInfo {
+    pub fn gather(path: &Path, needs: Needs) -> Result<Self, String> {
+        let probe = match needs.probe {
+            true => Some(Probe::run(path)?),
+            false => None,
+        };
+        let hash =
+            match needs.hash {
+                true => Some(content_hash(path).map_err(|err| {
+                    format!("Could not read {} to hash it: {err}", path.display())
+                })?),
+                false => None,
+            };
+
+        Ok(FileInfo { probe, hash })
+    }
+
+    /// `None` if the file wasn't probed or ffprobe doesn't know
+    pub fn duration(&self) -> Option<Duration> {
+        self.probe.as_ref()?.duration()
+    }
+
+    /// the value of a placeholder that needs ffprobe, see [`needs_probe`]. `None` if the file wasn't probed or
+    /// doesn't have the value.
+    pub fn probe_value(&self, name: &str) -> Option<String> {
+        let probe = self.probe.as_ref()?;
+
+        match name {
+            "width" => probe.width().map(|width| width.to_string()),
+            "height" => probe.height().map(|height| height.to_string()),
+            // whole seconds
+            "duration" => probe
+                .duration()
+                .map(|duration| duration.as_secs().to_string()),
+            "vcodec" => probe.video_codec().map(String::from),
+            "acodec" => probe.audio_codec().map(String::from),
+            // kb/s
+            "bitrate" => probe.bitrate().map(|bitrate| (bitrate / 1000).to_string()),
+            // up to two decimals, e.g. 25 or 29.97
+            "fps" => probe.fps().map(|fps| {
+                format!("{fps:.2}")
+                    .trim_end_matches('0')
+                    .trim_end_matches('.')
+                    .to_string()
+            }),
+            _ => {
+                let tag = name.strip_prefix("tag:")?;
+                let mut value = probe.tag(tag)?;
+
+                // track and disc numbers are often stored as `3/12`
+                if tag.eq_ignore_ascii_case("track") || tag.eq_ignore_ascii_case("disc") {
+                    value = value.split('/').next()?;
+                }
+
+                Some(value.to_string())
+            }
+        }
+    }
+}
+
+/// the values of all placeholders for a single file
+pub struct Placeholders<'a> {
+    input: &'a Input,
+    batch: &'a Batch,
+    info: &'a FileInfo,
+}
+
+impl<'a> Placeholders<'a> {
+    /// `info` has to be gathered with the [`Needs`] of the templates that are rendered
+    pub fn new(input: &'a Input, batch: &'a Batch, info: &'a FileInfo) -> Self {
+        Placeholders { input, batch, info }
+    }
+
+    /// `None` if the placeholder has no value for this file, e.g. {{ext}} for a file without an extension. parts of
+    /// the path are passed on as they are, even if they aren't valid unicode.
+    pub fn get(&self, name: &str) -> Option<OsString> {
+        if needs_probe(name) {
+            let value = self.info.probe_value(name)?;
+
+            // tags like `AC/DC` must not create directories
+            return Some(match name.starts_with("tag:") {
+                true => value.replace(['/', '\\'], "-").into(),
+                false => value.into(),
+            });
+        }
+
+        if let Some(value) = self.batch_value(name) {
+            return Some(value.into());
+        }
+
+        let path = &self.input.path;
+
+        let value = match name {
+            // {{ext}} -> extension
+            "ext" => path.extension()?,
+            // {{name}} -> filename without extension
+            "name" => path.file_stem()?,
+            // {{dir}} -> directory structure from starting point to file
+            "dir" => path.parent()?.as_os_str(),
+            // {{parent}} -> parent directory of starting point
+            "parent" => path.parent()?.file_name()?,
+            // {{reldir}} -> directory structure from the glob's base to file
+            "reldir" => return self.relative_dir(),
+            // {{hash}} -> start of the file's content hash
+            "hash" => return self.info.hash.clone().map(OsString::from),
+            _ => return None,
+        };
+
+        Some(value.to_os_string())
+    }
+
+    /// the file's directory relative to the input's base. the result is always relative (no root, no `..`), so it can
+    /// be put below a different directory even if the file's path is absolute or doesn't start with the base.
+    fn relative_dir(&self) -> Option<OsString> {
+        let dir = self.input.path.parent()?;
+        let dir = self
+            .input
+            .base
+            .as_deref()
+            .and_then(|base| dir.strip_prefix(base).ok())
+            .unwrap_or(dir);
+
+        let relative = dir
+            .components()
+            .filter(|component| matches!(component, Component::Normal(_)))
+            .collect::<PathBuf>();
+
+        Some(relative.into_os_string())
+    }
+
+    /// {{index}}, {{date}}, {{time}} and {{mtime}}, with or without a width or format after a colon
+    fn batch_value(&self, name: &str) -> Option<String> {
+        let (name, argument) = match name.split_once(':') {
+            Some((name, argument)) => (name, Some(argument)),
+            None => (name, None),
+        };
+
+        match name {
+            "index" => {
+                let width = match argument {
+                    Some(width) => width
+                        .parse()
+                        .ok()
+                        .filter(|width| *width <= MAX_INDEX_WIDTH)?,
+                    None => self.batch.file_count.to_string().len(),
+                };
+
+                Some(format!("{:0width$}", self.input.index))
+            }
+            "date" => format_time(&self.batch.started_at, argument.unwrap_or(DATE_FORMAT)),
+            "time" => format_time(&self.batch.started_at, argument.unwrap_or(TIME_FORMAT)),
+            "mtime" => {
+                let modified =
+                    fs::metadata(&self.input.path).and_then(|metadata| metadata.modified());
+
+                format_time(&modified.ok()?.into(), argument.unwrap_or(MTIME_FORMAT))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// the first [`HASH_LENGTH`] hex digits of the file's blake3 hash
+fn content_hash(path: &Path) -> io::Result<String> {
+    let mut hasher = blake3::Hasher::new();
+    io::copy(&mut File::open(path)?, &mut hasher)?;
+
+    Ok(hasher.finalize().to_hex()[..HASH_LENGTH].to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn value(name: &str) -> Option<OsString> {
+        let input = Input {
+            path: PathBuf::from("vids/a/clip.mp4"),
+            index: 3,
+            base: Some(PathBuf::from("vids")),
+        };
+        let batch = Batch {
+            file_count: 120,
+            started_at: Local::now(),
+        };
+        let info = FileInfo::default();
+
+        Placeholders::new(&input, &batch, &info).get(name)
+    }
+
+    #[test]
+    fn formats_that_only_parse_are_rejected() {
+        assert!(is_known("date:%Y %m"));
+        assert!(is_known("time:%H %:z"));
+        assert!(!is_known("date:%Y %:z %#z"));
+        assert!(!is_known("mtime:%Q"));
+        assert!(!is_known("date:"));
+    }
+
+    #[test]
+    fn index_width_is_capped() {
+        assert!(is_known("index:5"));
+        assert!(!is_known("index:99999999999999"));
+        assert_eq!(value("index"), Some("003".into()));
+        assert_eq!(value("index:5"), Some("00003".into()));
+        assert_eq!(value("index:99999999999999"), None);
+    }
+
+    #[test]
+    fn unformattable_dates_have_no_value() {
+        assert_eq!(value("date:%#z"), None);
+    }
+
+    #[test]
+    fn reldir_starts_below_the_base() {
+        assert_eq!(value("reldir"), Some("a".into()));
+        assert_eq!(value("dir"), Some("vids/a".into()));
+        assert_eq!(value("name"), Some("clip".into()));
+    }
+}
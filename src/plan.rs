@@ -0,0 +1,77 @@
+use crate::input::Input;
+use crate::placeholders::{Batch, FileInfo, Needs, Placeholders};
+use crate::template::Template;
+use std::ffi::OsString;
+use std::path::PathBuf;
+use std::thread;
+
+/// an input together with what will be done with it
+pub struct Job {
+    pub input: Input,
+    /// the filled in templates, or why they couldn't be filled in, e.g. because ffprobe failed
+    pub plan: Result<Plan, String>,
+}
+
+pub struct Plan {
+    pub output: PathBuf,
+    pub input_options: Vec<OsString>,
+}
+
+/// probes and hashes every file if `needs` says so, on up to `thread_count` threads. the results are in the same
+/// order as `paths`.
+pub fn gather(
+    paths: &[PathBuf],
+    needs: Needs,
+    thread_count: usize,
+) -> Vec<Result<FileInfo, String>> {
+    let chunk_size = paths.len().div_ceil(thread_count.max(1)).max(1);
+
+    thread::scope(|scope| {
+        let handles = paths
+            .chunks(chunk_size)
+            .map(|chunk| {
+                scope.spawn(move || {
+                    chunk
+                        .iter()
+                        .map(|path| FileInfo::gather(path, needs))
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect::<Vec<_>>();
+
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().unwrap())
+            .collect()
+    })
+}
+
+/// fills in the templates for every input before anything is processed, so all outputs are known up front. `infos`
+/// are the results of [`gather`] for the inputs, in the same order.
+pub fn resolve(
+    inputs: Vec<Input>,
+    infos: Vec<Result<FileInfo, String>>,
+    output_template: &Template,
+    input_option_templates: &[Template],
+    batch: &Batch,
+) -> Vec<Job> {
+    inputs
+        .into_iter()
+        .zip(infos)
+        .map(|(input, info)| {
+            let plan = info.map(|info| {
+                let placeholders = Placeholders::new(&input, batch, &info);
+
+                Plan {
+                    output: output_template.render(|name| placeholders.get(name)).into(),
+                    input_options: input_option_templates
+                        .iter()
+                        .map(|template| template.render(|name| placeholders.get(name)))
+                        .collect(),
+                }
+            });
+
+            Job { input, plan }
+        })
+        .collect()
+}
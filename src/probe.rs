@@ -0,0 +1,111 @@
+use serde_json::Value;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+/// what ffprobe knows about a file, i.e. its container and first video and audio stream
+pub struct Probe {
+    format: Value,
+    video: Option<Value>,
+    audio: Option<Value>,
+}
+
+impl Probe {
+    pub fn run(path: &Path) -> Result<Self, String> {
+        let output = Command::new("ffprobe")
+            .args([
+                "-v",
+                "error",
+                "-print_format",
+                "json",
+                "-show_format",
+                "-show_streams",
+            ])
+            .arg(path)
+            .stdin(Stdio::null())
+            .output()
+            .map_err(|err| format!("Could not run ffprobe: {err}"))?;
+
+        if !output.status.success() {
+            return Err(format!(
+                "ffprobe failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+
+        let mut info = serde_json::from_slice::<Value>(&output.stdout)
+            .map_err(|err| format!("Could not read ffprobe's output: {err}"))?;
+
+        let streams = match info["streams"].take() {
+            Value::Array(streams) => streams,
+            _ => vec![],
+        };
+        let first_stream = |codec_type: &str| {
+            streams
+                .iter()
+                .find(|stream| stream["codec_type"] == codec_type)
+                .cloned()
+        };
+
+        Ok(Probe {
+            video: first_stream("video"),
+            audio: first_stream("audio"),
+            format: info["format"].take(),
+        })
+    }
+
+    pub fn width(&self) -> Option<u64> {
+        self.video.as_ref()?["width"].as_u64()
+    }
+
+    pub fn height(&self) -> Option<u64> {
+        self.video.as_ref()?["height"].as_u64()
+    }
+
+    pub fn duration(&self) -> Option<Duration> {
+        let seconds = self.format["duration"].as_str()?.parse::<f64>().ok()?;
+
+        Duration::try_from_secs_f64(seconds).ok()
+    }
+
+    pub fn video_codec(&self) -> Option<&str> {
+        self.video.as_ref()?["codec_name"].as_str()
+    }
+
+    pub fn audio_codec(&self) -> Option<&str> {
+        self.audio.as_ref()?["codec_name"].as_str()
+    }
+
+    /// overall bitrate in bits per second
+    pub fn bitrate(&self) -> Option<u64> {
+        self.format["bit_rate"].as_str()?.parse().ok()
+    }
+
+    /// average frame rate of the video stream
+    pub fn fps(&self) -> Option<f64> {
+        let rate = self.video.as_ref()?["avg_frame_rate"].as_str()?;
+        let (numerator, denominator) = rate.split_once('/')?;
+        let (numerator, denominator) = (
+            numerator.parse::<f64>().ok()?,
+            denominator.parse::<f64>().ok()?,
+        );
+
+        if numerator > 0.0 && denominator > 0.0 {
+            Some(numerator / denominator)
+        } else {
+            None
+        }
+    }
+
+    /// a metadata tag like `artist`, ignoring case. looks at the container first, then at the streams, since some
+    /// formats (e.g. ogg) store them there.
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        [Some(&self.format), self.audio.as_ref(), self.video.as_ref()]
+            .into_iter()
+            .flatten()
+            .filter_map(|section| section["tags"].as_object())
+            .flat_map(|tags| tags.iter())
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .and_then(|(_, value)| value.as_str())
+    }
+}
@@ -0,0 +1,379 @@
+use clap::ValueEnum;
+use indicatif::{
+    HumanBytes, HumanDuration, MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle,
+};
+use serde_json::{json, Value};
+use std::fs;
+use std::io::IsTerminal;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// how many lines of ffmpeg's stderr end up in a `failed` json event
+const JSON_STDERR_LINES: usize = 10;
+/// how many lines of ffmpeg's stderr are written to the failed list per file
+pub const FAILED_LIST_STDERR_LINES: usize = 5;
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// progress bars on a terminal, plain log lines otherwise
+    Human,
+    /// one json event per line on stdout
+    Json,
+}
+
+/// everything ffzap tells the user while working. renders one bar per thread plus an overall bar when stdout is a
+/// terminal and falls back to plain `[THREAD n] -- ...` lines otherwise (e.g. when piped into a file). with
+/// `--output-format json`, every step is printed as a single line json event instead.
+pub struct Progress {
+    display: Display,
+    started_at: Instant,
+    succeeded: AtomicUsize,
+    failed: AtomicUsize,
+    skipped: AtomicUsize,
+    input_bytes: AtomicU64,
+    output_bytes: AtomicU64,
+}
+
+/// the outcome of a whole batch, see [`Progress::finish`]
+pub struct Summary {
+    pub succeeded: usize,
+    pub failed: usize,
+    pub skipped: usize,
+    pub elapsed: Duration,
+    /// size of all successfully processed input files
+    pub input_bytes: u64,
+    /// size of all files ffmpeg successfully wrote
+    pub output_bytes: u64,
+}
+
+enum Display {
+    Bars(Bars),
+    Lines,
+    Json,
+}
+
+struct Bars {
+    multi: MultiProgress,
+    overall: ProgressBar,
+    /// one per thread, more are added when threads are added while running
+    threads: Mutex<Vec<ProgressBar>>,
+    thread_style: ProgressStyle,
+}
+
+impl Progress {
+    pub fn new(format: OutputFormat, thread_count: usize, file_count: usize) -> Self {
+        let display = match format {
+            OutputFormat::Json => Display::Json,
+            OutputFormat::Human if std::io::stdout().is_terminal() => {
+                Display::Bars(Bars::new(thread_count, file_count))
+            }
+            OutputFormat::Human => Display::Lines,
+        };
+
+        Progress {
+            display,
+            started_at: Instant::now(),
+            succeeded: AtomicUsize::new(0),
+            failed: AtomicUsize::new(0),
+            skipped: AtomicUsize::new(0),
+            input_bytes: AtomicU64::new(0),
+            output_bytes: AtomicU64::new(0),
+        }
+    }
+
+    /// a file was added to the queue
+    pub fn queued(&self, path: &Path) {
+        if let Display::Json = self.display {
+            emit(json!({ "event": "queued", "input": path.to_string_lossy() }));
+        }
+    }
+
+    /// a file won't be processed at all
+    pub fn skipped(&self, path: &Path, reason: &str) {
+        self.skipped.fetch_add(1, Ordering::Relaxed);
+
+        match &self.display {
+            Display::Bars(bars) => {
+                bars.multi
+                    .suspend(|| println!("Skipping {}, {reason}", path.display()));
+                bars.overall.inc(1);
+            }
+            Display::Lines => println!("Skipping {}, {reason}", path.display()),
+            Display::Json => emit(
+                json!({ "event": "skipped", "input": path.to_string_lossy(), "reason": reason }),
+            ),
+        }
+    }
+
+    pub fn started(&self, thread: usize, path: &Path) {
+        match &self.display {
+            Display::Bars(bars) => {
+                let bar = bars.thread(thread);
+                bar.reset();
+                bar.set_length(0);
+                bar.set_message(path.display().to_string());
+            }
+            Display::Lines => println!("[THREAD {thread}] -- Processing {}", path.display()),
+            Display::Json => emit(
+                json!({ "event": "started", "thread": thread, "input": path.to_string_lossy() }),
+            ),
+        }
+    }
+
+    /// `time` is how far ffmpeg got into the file, `duration` is the file's total length if known
+    pub fn advanced(&self, thread: usize, path: &Path, time: Duration, duration: Option<Duration>) {
+        match &self.display {
+            Display::Bars(bars) => {
+                if let Some(duration) = duration {
+                    let bar = bars.thread(thread);
+                    bar.set_length(duration.as_millis() as u64);
+                    bar.set_position((time.as_millis() as u64).min(duration.as_millis() as u64));
+                }
+            }
+            Display::Lines => {}
+            Display::Json => emit(json!({
+                "event": "progress",
+                "thread": thread,
+                "input": path.to_string_lossy(),
+                "time": time.as_secs_f64(),
+                "duration": duration.map(|duration| duration.as_secs_f64()),
+            })),
+        }
+    }
+
+    pub fn succeeded(&self, thread: usize, path: &Path, output: &Path) {
+        self.succeeded.fetch_add(1, Ordering::Relaxed);
+        self.input_bytes
+            .fetch_add(file_size(path), Ordering::Relaxed);
+        self.output_bytes
+            .fetch_add(file_size(output), Ordering::Relaxed);
+
+        match &self.display {
+            Display::Json => emit(json!({
+                "event": "succeeded",
+                "thread": thread,
+                "input": path.to_string_lossy(),
+                "output": output.to_string_lossy(),
+            })),
+            _ => self.println(format!(
+                "[THREAD {thread}] -- Success, saving to {}",
+                output.display()
+            )),
+        }
+        self.file_done(thread);
+    }
+
+    /// `exit_code` is `None` if ffmpeg couldn't be started or was killed by a signal
+    pub fn failed(&self, thread: usize, path: &Path, exit_code: Option<i32>, error: &str) {
+        self.failed.fetch_add(1, Ordering::Relaxed);
+
+        match &self.display {
+            Display::Json => emit(json!({
+                "event": "failed",
+                "thread": thread,
+                "input": path.to_string_lossy(),
+                "exit_code": exit_code,
+                "stderr": tail(error, JSON_STDERR_LINES),
+            })),
+            _ => {
+                self.eprintln(format!("[THREAD {thread}] -- Error!"));
+                self.eprintln(format!("[THREAD {thread}] -- Error is: {error}"));
+                self.eprintln(format!(
+                    "[THREAD {thread}] -- Continuing with next task if there's more to do..."
+                ));
+            }
+        }
+        self.file_done(thread);
+    }
+
+    /// the number of files that are processed at the same time changed, see --adaptive
+    pub fn concurrency_changed(&self, limit: usize, reason: &str) {
+        match &self.display {
+            Display::Json => emit(json!({
+                "event": "concurrency",
+                "limit": limit,
+                "reason": reason,
+            })),
+            _ => self.println(format!(
+                "Processing up to {limit} files at a time, {reason}"
+            )),
+        }
+    }
+
+    /// threads stopped or went back to starting new files, see --control
+    pub fn paused(&self, paused: bool) {
+        match &self.display {
+            Display::Json => emit(json!({ "event": if paused { "paused" } else { "resumed" } })),
+            _ if paused => self.println(
+                "Paused, running files are finished but no new ones are started".to_string(),
+            ),
+            _ => self.println("Resumed".to_string()),
+        }
+    }
+
+    /// the thread ran out of work
+    pub fn thread_finished(&self, thread: usize) {
+        if let Display::Bars(bars) = &self.display {
+            bars.thread(thread).finish_and_clear();
+        }
+    }
+
+    /// all threads are done. leaves the overall bar on screen in its final state and prints the summary.
+    pub fn finish(&self) -> Summary {
+        let summary = self.summary();
+
+        match &self.display {
+            Display::Bars(bars) => {
+                bars.overall.finish();
+                print_summary(&summary);
+            }
+            Display::Lines => print_summary(&summary),
+            Display::Json => emit(json!({
+                "event": "summary",
+                "total": summary.total(),
+                "succeeded": summary.succeeded,
+                "failed": summary.failed,
+                "skipped": summary.skipped,
+                "elapsed": summary.elapsed.as_secs_f64(),
+                "input_bytes": summary.input_bytes,
+                "output_bytes": summary.output_bytes,
+            })),
+        }
+
+        summary
+    }
+
+    /// the counts so far
+    pub fn summary(&self) -> Summary {
+        Summary {
+            succeeded: self.succeeded.load(Ordering::Relaxed),
+            failed: self.failed.load(Ordering::Relaxed),
+            skipped: self.skipped.load(Ordering::Relaxed),
+            elapsed: self.started_at.elapsed(),
+            input_bytes: self.input_bytes.load(Ordering::Relaxed),
+            output_bytes: self.output_bytes.load(Ordering::Relaxed),
+        }
+    }
+
+    /// prints a message for the user. swallowed in json mode to keep stdout parseable.
+    pub fn println(&self, message: String) {
+        match &self.display {
+            Display::Bars(bars) => bars.multi.suspend(|| println!("{message}")),
+            Display::Lines => println!("{message}"),
+            Display::Json => {}
+        }
+    }
+
+    pub fn eprintln(&self, message: String) {
+        match &self.display {
+            Display::Bars(bars) => bars.multi.suspend(|| eprintln!("{message}")),
+            Display::Lines | Display::Json => eprintln!("{message}"),
+        }
+    }
+
+    fn file_done(&self, thread: usize) {
+        if let Display::Bars(bars) = &self.display {
+            let bar = bars.thread(thread);
+            bar.reset();
+            bar.set_length(0);
+            bar.set_message("idle");
+            bars.overall.inc(1);
+        }
+    }
+}
+
+impl Summary {
+    pub fn total(&self) -> usize {
+        self.succeeded + self.failed + self.skipped
+    }
+}
+
+impl Bars {
+    fn new(thread_count: usize, file_count: usize) -> Self {
+        let multi = MultiProgress::with_draw_target(ProgressDrawTarget::stdout());
+
+        let thread_style = ProgressStyle::with_template(
+            "[THREAD {prefix}] [{bar:30.cyan/blue}] {percent:>3}% {msg}",
+        )
+        .unwrap()
+        .progress_chars("=> ");
+        let threads = (0..thread_count)
+            .map(|thread| {
+                let bar = multi.add(ProgressBar::new(0));
+                bar.set_style(thread_style.clone());
+                bar.set_prefix(thread.to_string());
+                bar.set_message("idle");
+                bar
+            })
+            .collect();
+
+        let overall = multi.add(ProgressBar::new(file_count as u64));
+        overall.set_style(
+            ProgressStyle::with_template(
+                "[{elapsed_precise}] [{wide_bar:.green}] {pos}/{len} files done, ETA {eta}",
+            )
+            .unwrap()
+            .progress_chars("=> "),
+        );
+        overall.enable_steady_tick(Duration::from_secs(1));
+
+        Bars {
+            multi,
+            overall,
+            threads: Mutex::new(threads),
+            thread_style,
+        }
+    }
+
+    /// the bar of `thread`, adding bars above the overall one for threads that were started later
+    fn thread(&self, thread: usize) -> ProgressBar {
+        let mut threads = self.threads.lock().unwrap();
+
+        while threads.len() <= thread {
+            let bar = self.multi.insert_before(&self.overall, ProgressBar::new(0));
+            bar.set_style(self.thread_style.clone());
+            bar.set_prefix(threads.len().to_string());
+            bar.set_message("idle");
+            threads.push(bar);
+        }
+
+        threads[thread].clone()
+    }
+}
+
+/// prints a single event. `println!` locks stdout, so events from different threads never interleave.
+fn emit(event: Value) {
+    println!("{event}");
+}
+
+fn print_summary(summary: &Summary) {
+    println!(
+        "Done in {}: {} succeeded, {} failed, {} skipped ({} total)",
+        HumanDuration(summary.elapsed),
+        summary.succeeded,
+        summary.failed,
+        summary.skipped,
+        summary.total()
+    );
+    println!(
+        "Processed {} of input into {} of output",
+        HumanBytes(summary.input_bytes),
+        HumanBytes(summary.output_bytes)
+    );
+}
+
+/// 0 if the file doesn't exist (anymore), the summary is best effort
+fn file_size(path: &Path) -> u64 {
+    fs::metadata(path)
+        .map(|metadata| metadata.len())
+        .unwrap_or(0)
+}
+
+/// the last `lines` lines of `text`
+pub fn tail(text: &str, lines: usize) -> String {
+    let all = text.lines().collect::<Vec<&str>>();
+
+    all[all.len().saturating_sub(lines)..].join("\n")
+}
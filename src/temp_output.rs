@@ -0,0 +1,61 @@
+use crate::on_exists;
+use std::ffi::OsString;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// the file ffmpeg actually writes to. it sits right next to the real output and has the same extension so ffmpeg
+/// still picks the right muxer, but is only moved into place once ffmpeg succeeded. that way a crashed or killed
+/// ffmpeg never leaves a truncated file behind that looks like a finished one.
+///
+/// if it isn't persisted, the temporary file is deleted when this is dropped.
+pub struct TempOutput {
+    pub path: PathBuf,
+    destination: PathBuf,
+    /// move to a free name next to the destination if it exists, see [`crate::on_exists::OnExists::Rename`]
+    rename: bool,
+    persisted: bool,
+}
+
+impl TempOutput {
+    pub fn new(destination: &Path, rename: bool) -> Self {
+        let mut file_name = OsString::from(".");
+        file_name.push(destination.file_stem().unwrap_or_default());
+        file_name.push(format!(".ffzap-{:08x}", rand::random::<u32>()));
+        if let Some(extension) = destination.extension() {
+            file_name.push(".");
+            file_name.push(extension);
+        }
+
+        TempOutput {
+            path: destination.with_file_name(file_name),
+            destination: destination.to_path_buf(),
+            rename,
+            persisted: false,
+        }
+    }
+
+    /// moves the temporary file to its destination and returns where it ended up. replaces whatever is there, unless
+    /// it was created with `rename`.
+    pub fn persist(mut self) -> io::Result<PathBuf> {
+        let destination = match self.rename {
+            true => on_exists::move_to_free_name(&self.path, &self.destination)?,
+            false => {
+                fs::rename(&self.path, &self.destination)?;
+                self.destination.clone()
+            }
+        };
+        self.persisted = true;
+
+        Ok(destination)
+    }
+}
+
+impl Drop for TempOutput {
+    fn drop(&mut self) {
+        if !self.persisted {
+            // ffmpeg might not have created it at all
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
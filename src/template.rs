@@ -0,0 +1,414 @@
+use std::error::Error;
+use std::ffi::OsString;
+use std::fmt::{Display, Formatter};
+
+/// a parsed template like `--output`. placeholders are written as `{{name}}` and can be passed through filters like
+/// `{{name|lower|replace:' ':'_'}}`. a quoted string instead of a name is inserted as is, which is how literal braces
+/// are written: `{{'{{'}}`. arguments of placeholders go up to the first `|` or `}}` and can contain spaces, like
+/// `{{date:%d %b %Y}}`, or be quoted like `{{date:' %H:%M'}}` to keep spaces at the start or end.
+///
+/// filters:
+///
+/// - `lower`, `upper`: changes the case
+/// - `replace:'from':'to'`: replaces every occurrence of `from` with `to`
+/// - `slug`: lowercase letters and digits only, everything else becomes a single `-`
+/// - `truncate:n`: keeps the first n characters
+/// - `default:'value'`: used if the placeholder has no or an empty value
+#[derive(Debug, Clone)]
+pub struct Template {
+    parts: Vec<Part>,
+}
+
+#[derive(Debug, Clone)]
+enum Part {
+    Text(String),
+    Placeholder {
+        source: Source,
+        filters: Vec<Filter>,
+    },
+}
+
+#[derive(Debug, Clone)]
+enum Source {
+    Name(String),
+    Literal(String),
+}
+
+#[derive(Debug, Clone)]
+enum Filter {
+    Lower,
+    Upper,
+    Replace(String, String),
+    Slug,
+    Truncate(usize),
+    Default(String),
+}
+
+#[derive(Debug)]
+pub struct TemplateError(String);
+
+impl Template {
+    /// parses `template`, failing on syntax errors, unknown filters and placeholders for which `is_known` is false
+    pub fn parse(template: &str, is_known: impl Fn(&str) -> bool) -> Result<Self, TemplateError> {
+        let mut parts = vec![];
+        let mut rest = template;
+
+        while let Some(start) = rest.find("{{") {
+            if start > 0 {
+                parts.push(Part::Text(rest[..start].to_string()));
+            }
+
+            let mut parser = Parser {
+                chars: rest[start + 2..].char_indices().peekable(),
+                source: &rest[start + 2..],
+            };
+            let (part, length) = parser.placeholder()?;
+
+            if let Part::Placeholder {
+                source: Source::Name(name),
+                ..
+            } = &part
+            {
+                if !is_known(name) {
+                    return Err(TemplateError(format!("unknown placeholder {{{{{name}}}}}")));
+                }
+            }
+
+            parts.push(part);
+            rest = &rest[start + 2 + length..];
+        }
+
+        if !rest.is_empty() {
+            parts.push(Part::Text(rest.to_string()));
+        }
+
+        Ok(Template { parts })
+    }
+
+    /// whether any placeholder of the template matches `predicate`
+    pub fn uses(&self, predicate: impl Fn(&str) -> bool) -> bool {
+        self.parts.iter().any(|part| match part {
+            Part::Placeholder {
+                source: Source::Name(name),
+                ..
+            } => predicate(name),
+            _ => false,
+        })
+    }
+
+    /// fills in the placeholders. `value` returns `None` for placeholders that have no value for the current file,
+    /// which are left empty unless a `default` filter says otherwise. values are inserted as they are, even if they
+    /// aren't valid unicode (like some file names). only filters replace the invalid parts, since they work on text.
+    pub fn render(&self, value: impl Fn(&str) -> Option<OsString>) -> OsString {
+        let mut rendered = OsString::new();
+
+        for part in &self.parts {
+            match part {
+                Part::Text(text) => rendered.push(text),
+                Part::Placeholder { source, filters } => {
+                    let current = match source {
+                        Source::Name(name) => value(name),
+                        Source::Literal(literal) => Some(literal.into()),
+                    };
+
+                    if filters.is_empty() {
+                        rendered.push(current.unwrap_or_default());
+                        continue;
+                    }
+
+                    let mut current = current.map(|current| current.to_string_lossy().into_owned());
+                    for filter in filters {
+                        current = filter.apply(current);
+                    }
+
+                    rendered.push(current.unwrap_or_default());
+                }
+            }
+        }
+
+        rendered
+    }
+}
+
+impl Filter {
+    fn apply(&self, value: Option<String>) -> Option<String> {
+        match self {
+            Filter::Default(default) => match value {
+                Some(value) if !value.is_empty() => Some(value),
+                _ => Some(default.clone()),
+            },
+            Filter::Lower => value.map(|value| value.to_lowercase()),
+            Filter::Upper => value.map(|value| value.to_uppercase()),
+            Filter::Replace(from, to) => value.map(|value| value.replace(from, to)),
+            Filter::Slug => value.map(|value| slug(&value)),
+            Filter::Truncate(length) => value.map(|value| value.chars().take(*length).collect()),
+        }
+    }
+}
+
+fn slug(value: &str) -> String {
+    let mut slug = String::new();
+
+    for char in value.chars().flat_map(char::to_lowercase) {
+        if char.is_alphanumeric() {
+            slug.push(char);
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+
+    slug.trim_end_matches('-').to_string()
+}
+
+/// parses everything after an opening `{{`
+struct Parser<'a> {
+    chars: std::iter::Peekable<std::str::CharIndices<'a>>,
+    source: &'a str,
+}
+
+impl Parser<'_> {
+    /// returns the placeholder and how many bytes it took up, including the closing `}}`
+    fn placeholder(&mut self) -> Result<(Part, usize), TemplateError> {
+        self.skip_whitespace();
+
+        let source = match self.chars.peek() {
+            Some((_, '\'' | '"')) => Source::Literal(self.quoted()?),
+            _ => {
+                let mut name = self.bare(&['|', '}', ':']);
+                if name.is_empty() {
+                    return Err(TemplateError("empty placeholder {{}}".to_string()));
+                }
+
+                // the argument of e.g. `{{date:%d %b %Y}}` or `{{tag:album artist}}` can contain spaces, quotes keep
+                // them at the start or end
+                if let Some((_, ':')) = self.chars.peek() {
+                    self.chars.next();
+                    self.skip_whitespace();
+
+                    let argument = match self.chars.peek() {
+                        Some((_, '\'' | '"')) => self.quoted()?,
+                        _ => self.until(&['|', '}']).trim_end().to_string(),
+                    };
+                    name.push(':');
+                    name.push_str(&argument);
+                }
+
+                Source::Name(name)
+            }
+        };
+
+        let mut filters = vec![];
+
+        loop {
+            self.skip_whitespace();
+
+            match self.chars.next() {
+                Some((_, '|')) => filters.push(self.filter()?),
+                Some((index, '}')) => {
+                    return match self.chars.next() {
+                        Some((_, '}')) => Ok((Part::Placeholder { source, filters }, index + 2)),
+                        _ => Err(self.unclosed()),
+                    };
+                }
+                Some((_, char)) => {
+                    return Err(TemplateError(format!(
+                        "unexpected '{char}' in placeholder {{{{{}",
+                        self.source
+                    )))
+                }
+                None => return Err(self.unclosed()),
+            }
+        }
+    }
+
+    fn filter(&mut self) -> Result<Filter, TemplateError> {
+        self.skip_whitespace();
+        let name = self.bare(&['|', '}', ':']);
+
+        let mut arguments = vec![];
+        while let Some((_, ':')) = self.chars.peek() {
+            self.chars.next();
+            self.skip_whitespace();
+
+            arguments.push(match self.chars.peek() {
+                Some((_, '\'' | '"')) => self.quoted()?,
+                _ => self.bare(&['|', '}', ':']),
+            });
+        }
+
+        let expect_arguments = |count: usize| {
+            if arguments.len() == count {
+                Ok(())
+            } else {
+                Err(TemplateError(format!(
+                    "filter '{name}' takes {count} argument(s), got {}",
+                    arguments.len()
+                )))
+            }
+        };
+
+        match name.as_str() {
+            "lower" => expect_arguments(0).map(|_| Filter::Lower),
+            "upper" => expect_arguments(0).map(|_| Filter::Upper),
+            "slug" => expect_arguments(0).map(|_| Filter::Slug),
+            "replace" => expect_arguments(2)
+                .map(|_| Filter::Replace(arguments[0].clone(), arguments[1].clone())),
+            "default" => expect_arguments(1).map(|_| Filter::Default(arguments[0].clone())),
+            "truncate" => {
+                expect_arguments(1)?;
+
+                arguments[0].parse().map(Filter::Truncate).map_err(|_| {
+                    TemplateError(format!(
+                        "filter 'truncate' needs a number, got '{}'",
+                        arguments[0]
+                    ))
+                })
+            }
+            _ => Err(TemplateError(format!("unknown filter '{name}'"))),
+        }
+    }
+
+    /// an unquoted name or argument, up to one of `stop` or whitespace
+    fn bare(&mut self, stop: &[char]) -> String {
+        let mut bare = String::new();
+
+        while let Some((_, char)) = self.chars.peek() {
+            if stop.contains(char) || char.is_whitespace() {
+                break;
+            }
+
+            bare.push(*char);
+            self.chars.next();
+        }
+
+        bare
+    }
+
+    /// everything up to one of `stop`, including whitespace
+    fn until(&mut self, stop: &[char]) -> String {
+        let mut until = String::new();
+
+        while let Some((_, char)) = self.chars.next_if(|(_, char)| !stop.contains(char)) {
+            until.push(char);
+        }
+
+        until
+    }
+
+    /// a string in single or double quotes, without the quotes
+    fn quoted(&mut self) -> Result<String, TemplateError> {
+        let (_, quote) = self.chars.next().expect("called on a quote");
+        let mut quoted = String::new();
+
+        for (_, char) in self.chars.by_ref() {
+            if char == quote {
+                return Ok(quoted);
+            }
+
+            quoted.push(char);
+        }
+
+        Err(TemplateError(format!(
+            "missing closing {quote} in placeholder {{{{{}",
+            self.source
+        )))
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some((_, char)) = self.chars.peek() {
+            if !char.is_whitespace() {
+                break;
+            }
+
+            self.chars.next();
+        }
+    }
+
+    fn unclosed(&self) -> TemplateError {
+        TemplateError(format!(
+            "missing closing }}}} in placeholder {{{{{}",
+            self.source
+        ))
+    }
+}
+
+impl Display for TemplateError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Error for TemplateError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(template: &str) -> String {
+        Template::parse(template, |_| true)
+            .unwrap()
+            .render(|name| match name {
+                "name" => Some("My Clip".into()),
+                "ext" => None,
+                _ => Some(format!("<{name}>").into()),
+            })
+            .into_string()
+            .unwrap()
+    }
+
+    fn error(template: &str) -> String {
+        Template::parse(template, |name| name == "name")
+            .unwrap_err()
+            .to_string()
+    }
+
+    #[test]
+    fn fills_in_placeholders() {
+        assert_eq!(render("out/{{name}}.{{ext}}"), "out/My Clip.");
+        assert_eq!(render("{{ name }}"), "My Clip");
+    }
+
+    #[test]
+    fn applies_filters_in_order() {
+        assert_eq!(render("{{name|replace:' ':'_'}}"), "My_Clip");
+        assert_eq!(render("{{name|lower|replace:' ':'-'}}"), "my-clip");
+        assert_eq!(render("{{name|upper}}"), "MY CLIP");
+        assert_eq!(render("{{name|slug}}"), "my-clip");
+        assert_eq!(render("{{name|truncate:2}}"), "My");
+        assert_eq!(render("{{ext|default:'mp4'}}"), "mp4");
+        assert_eq!(render("{{name|default:'mp4'}}"), "My Clip");
+    }
+
+    #[test]
+    fn quoted_strings_are_literals() {
+        assert_eq!(render("{{'{{'}}name{{'}}'}}"), "{{name}}");
+        assert_eq!(render("{{\"a|b\"}}"), "a|b");
+    }
+
+    #[test]
+    fn arguments_can_contain_spaces() {
+        assert_eq!(render("{{date:%Y %m}}"), "<date:%Y %m>");
+        assert_eq!(render("{{date:' %d '|upper}}"), "<DATE: %D >");
+        assert_eq!(render("{{tag:album artist}}"), "<tag:album artist>");
+    }
+
+    #[test]
+    fn reports_mistakes() {
+        assert_eq!(error("{{nmae}}"), "unknown placeholder {{nmae}}");
+        assert_eq!(error("{{name|shout}}"), "unknown filter 'shout'");
+        assert_eq!(
+            error("{{name|replace:'a'}}"),
+            "filter 'replace' takes 2 argument(s), got 1"
+        );
+        assert_eq!(
+            error("{{name|truncate:many}}"),
+            "filter 'truncate' needs a number, got 'many'"
+        );
+        assert_eq!(error("{{}}"), "empty placeholder {{}}");
+        assert_eq!(error("{{name"), "missing closing }} in placeholder {{name");
+        assert_eq!(
+            error("{{'name}}"),
+            "missing closing ' in placeholder {{'name}}"
+        );
+    }
+}
@@ -0,0 +1,54 @@
+use std::num::NonZeroUsize;
+use std::thread::available_parallelism;
+
+/// how many files are processed at the same time, as given to --thread-count
+#[derive(Debug, Clone, Copy)]
+pub enum Threads {
+    /// one per available core
+    Auto,
+    Count(usize),
+    /// a share of the available cores, e.g. 0.5 for `50%`
+    Share(f64),
+}
+
+impl Threads {
+    /// `auto`, a number like `8` or a percentage of the available cores like `50%`
+    pub fn parse(threads: &str) -> Result<Self, String> {
+        let threads = threads.trim();
+
+        if threads.eq_ignore_ascii_case("auto") {
+            return Ok(Threads::Auto);
+        }
+
+        if let Some(percent) = threads.strip_suffix('%') {
+            return match percent.trim().parse::<f64>() {
+                Ok(percent) if percent > 0.0 && percent.is_finite() => {
+                    Ok(Threads::Share(percent / 100.0))
+                }
+                _ => Err(format!("'{threads}' isn't a positive percentage")),
+            };
+        }
+
+        match threads.parse::<usize>() {
+            Ok(count) if count > 0 => Ok(Threads::Count(count)),
+            _ => Err(format!(
+                "'{threads}' is neither a positive number, a percentage like 50% nor auto"
+            )),
+        }
+    }
+
+    /// the actual number of threads, at least one
+    pub fn count(self) -> usize {
+        match self {
+            Threads::Count(count) => count,
+            Threads::Auto => cores(),
+            Threads::Share(share) => ((cores() as f64 * share).round() as usize).max(1),
+        }
+    }
+}
+
+/// the cores ffzap may use. on linux, std already takes the cgroup cpu quota (e.g. of a container) and the cpu
+/// affinity into account.
+pub fn cores() -> usize {
+    available_parallelism().map_or(1, NonZeroUsize::get)
+}
@@ -0,0 +1,273 @@
+use crate::concurrency::Concurrency;
+use crate::ffmpeg;
+use crate::interrupt::Interrupt;
+use crate::journal::{JobState, Journal};
+use crate::lists::FailedList;
+use crate::on_exists::{self, Decision, OnExists};
+use crate::plan::Job;
+use crate::progress::Progress;
+use crate::temp_output::TempOutput;
+use crate::threads;
+use std::fs::create_dir_all;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// how often a thread over the concurrency limit checks whether it may continue
+const WAIT_FOR_LIMIT: Duration = Duration::from_millis(200);
+/// the most threads [`Workers::resize`] starts, far more ffmpeg processes than any machine can run at once
+pub const MAX_WORKERS: usize = 256;
+
+/// the threads running ffmpeg, each taking the next job from the queue until it's empty. more threads can be added
+/// while the batch is running, see [`Workers::resize`].
+pub struct Workers {
+    /// the jobs still to do, the next one at the back
+    jobs: Mutex<Vec<Job>>,
+    /// jobs that were taken from the queue but aren't done yet
+    running: AtomicUsize,
+    handles: Mutex<Vec<Option<JoinHandle<()>>>>,
+    pub concurrency: Arc<Concurrency>,
+    pub progress: Arc<Progress>,
+    interrupt: Arc<Interrupt>,
+    failed_list: Option<FailedList>,
+    journal: Option<Journal>,
+    settings: Settings,
+}
+
+/// how every job is processed
+pub struct Settings {
+    pub ffmpeg_options: Vec<String>,
+    pub on_exists: OnExists,
+    pub incremental: bool,
+    /// split the cores between the files processed at the same time, see --share-cores
+    pub share_cores: bool,
+}
+
+impl Workers {
+    /// `jobs` are processed from front to back
+    pub fn new(
+        mut jobs: Vec<Job>,
+        concurrency: Arc<Concurrency>,
+        progress: Arc<Progress>,
+        interrupt: Arc<Interrupt>,
+        failed_list: Option<FailedList>,
+        journal: Option<Journal>,
+        settings: Settings,
+    ) -> Self {
+        // threads take their next job from the back
+        jobs.reverse();
+
+        Workers {
+            jobs: Mutex::new(jobs),
+            running: AtomicUsize::new(0),
+            handles: Mutex::new(vec![]),
+            concurrency,
+            progress,
+            interrupt,
+            failed_list,
+            journal,
+            settings,
+        }
+    }
+
+    /// starts threads until there are `count` of them. threads never go away before the queue is empty, fewer are
+    /// used by lowering the concurrency limit instead. if the system refuses to start a thread, the ones started until
+    /// then keep working.
+    pub fn spawn(self: &Arc<Self>, count: usize) -> io::Result<()> {
+        let mut handles = self.handles.lock().unwrap();
+
+        for thread in handles.len()..count {
+            let workers = Arc::clone(self);
+            let handle = thread::Builder::new().spawn(move || workers.run(thread))?;
+            handles.push(Some(handle));
+        }
+
+        Ok(())
+    }
+
+    /// how many threads were started so far
+    pub fn spawned(&self) -> usize {
+        self.handles.lock().unwrap().len()
+    }
+
+    /// sets the number of threads working at the same time, starting new ones if needed. returns the new number.
+    pub fn resize(self: &Arc<Self>, count: usize) -> Result<usize, String> {
+        if count > MAX_WORKERS {
+            return Err(format!("at most {MAX_WORKERS} workers are possible"));
+        }
+
+        let count = self.concurrency.set_workers(count);
+        if let Err(err) = self.spawn(count) {
+            let spawned = self.concurrency.set_workers(self.spawned());
+            return Err(format!("could only start {spawned} workers: {err}"));
+        }
+
+        Ok(count)
+    }
+
+    /// waits until all threads are done, including those started while waiting
+    pub fn join(&self) {
+        loop {
+            let handle = self
+                .handles
+                .lock()
+                .unwrap()
+                .iter_mut()
+                .find_map(Option::take);
+
+            match handle {
+                Some(handle) => handle.join().unwrap(),
+                None => break,
+            }
+        }
+    }
+
+    /// the number of jobs that weren't started yet
+    pub fn queued(&self) -> usize {
+        self.jobs.lock().unwrap().len()
+    }
+
+    /// the number of jobs being processed right now
+    pub fn running(&self) -> usize {
+        self.running.load(Ordering::SeqCst)
+    }
+
+    fn run(&self, thread: usize) {
+        loop {
+            let job_to_process = loop {
+                if self.interrupt.stopping() {
+                    break None;
+                }
+
+                let mut queue = self.jobs.lock().unwrap();
+                if queue.is_empty() || self.concurrency.allows(thread) {
+                    break queue.pop();
+                }
+
+                // over the limit or paused, see --adaptive and --control
+                drop(queue);
+                thread::sleep(WAIT_FOR_LIMIT);
+            };
+
+            match job_to_process {
+                Some(job) => {
+                    self.running.fetch_add(1, Ordering::SeqCst);
+                    self.process(thread, job);
+                    self.running.fetch_sub(1, Ordering::SeqCst);
+                }
+                None => {
+                    self.progress.thread_finished(thread);
+                    break;
+                }
+            }
+        }
+    }
+
+    fn process(&self, thread: usize, job: Job) {
+        let progress = &self.progress;
+        let path = &job.input.path;
+        let (final_file_name, rename, input_options, refusal) = match job.plan {
+            Err(error) => (PathBuf::new(), false, vec![], Some(error)),
+            Ok(plan) => match on_exists::decide(
+                path,
+                &plan.output,
+                self.settings.on_exists,
+                self.settings.incremental,
+            ) {
+                Decision::Write { output, rename } => (output, rename, plan.input_options, None),
+                Decision::Skip(reason) => {
+                    progress.skipped(path, &reason);
+                    return;
+                }
+                Decision::Fail(error) => (plan.output, false, plan.input_options, Some(error)),
+            },
+        };
+
+        progress.started(thread, path);
+        let final_path_parent = Path::new(&final_file_name)
+            .parent()
+            .filter(|parent| !parent.exists());
+
+        if let (None, Some(final_path_parent)) = (&refusal, final_path_parent) {
+            match create_dir_all(final_path_parent) {
+                Ok(_) => {}
+                Err(err) => {
+                    progress.eprintln(format!(
+                        "[THREAD {thread}] -- Could not create directory structure for file {}",
+                        final_file_name.display()
+                    ));
+                    progress.eprintln(err.to_string())
+                }
+            }
+        }
+
+        let record = |state: JobState, output: &Path| {
+            if let Some(journal) = &self.journal {
+                if let Err(err) = journal.record(path, state, Some(output)) {
+                    progress.eprintln(format!(
+                        "[THREAD {thread}] -- Could not update the journal: {err}"
+                    ));
+                }
+            }
+        };
+        record(JobState::Running, &final_file_name);
+
+        // taken per file, the number of files processed at the same time can change while running
+        let ffmpeg_threads = self
+            .settings
+            .share_cores
+            .then(|| (threads::cores() / self.concurrency.limit()).max(1));
+
+        let result = match refusal {
+            Some(error) => Err((None, error)),
+            None => {
+                let temp_output = TempOutput::new(&final_file_name, rename);
+
+                match ffmpeg::run(
+                    path,
+                    &input_options,
+                    &self.settings.ffmpeg_options,
+                    &temp_output.path,
+                    ffmpeg_threads,
+                    &self.interrupt,
+                    |time, duration| progress.advanced(thread, path, time, duration),
+                ) {
+                    Ok(output) if output.status.success() => {
+                        temp_output.persist().map_err(|err| {
+                            (None, format!("Could not move the finished file to {}: {err}", final_file_name.display()))
+                        })
+                    }
+                    Ok(_) if self.interrupt.aborted() => {
+                        Err((None, "Aborted by Ctrl-C".to_string()))
+                    }
+                    Ok(output) => Err((output.status.code(), output.stderr)),
+                    Err(_) => Err((None, "There was an error running ffmpeg. Please check if it's correctly installed and working as intended.".to_string())),
+                }
+            }
+        };
+
+        match result {
+            // differs from the planned name if --on-exists rename had to pick a free one
+            Ok(written) => {
+                record(JobState::Done, &written);
+                progress.succeeded(thread, path, &written);
+            }
+            Err((exit_code, error)) => {
+                record(JobState::Failed, &final_file_name);
+                progress.failed(thread, path, exit_code, &error);
+
+                if let Some(failed_list) = &self.failed_list {
+                    if let Err(err) = failed_list.record(path, exit_code, &error) {
+                        progress.eprintln(format!(
+                            "[THREAD {thread}] -- Could not write {} to the failed list: {err}",
+                            path.display()
+                        ));
+                    }
+                }
+            }
+        }
+    }
+}